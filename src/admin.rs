@@ -0,0 +1,110 @@
+use crate::*;
+
+/// Maximum fee charged by the contract, 1000=10%
+pub const MAX_CONTRACT_FEE: u64 = 1_000;
+/// Maximum interest of a loan, 5000=50%
+pub const MAX_CONTRACT_INTEREST: u64 = 5_000;
+/// Shortest payment period allowed for a loan (1 day)
+pub const MIN_PAYMENT_PERIOD: u64 = 1_000_000_000 * 60 * 60 * 24;
+/// Longest payment period allowed for a loan (365 days)
+pub const MAX_PAYMENT_PERIOD: u64 = 1_000_000_000 * 60 * 60 * 24 * 365;
+
+pub(crate) fn assert_valid_interest(contract_interest: u64) {
+    assert!(
+        contract_interest <= MAX_CONTRACT_INTEREST,
+        "The interest cannot be greater than {}",
+        MAX_CONTRACT_INTEREST
+    );
+}
+
+pub(crate) fn assert_valid_fee(contract_fee: u64) {
+    assert!(
+        contract_fee <= MAX_CONTRACT_FEE,
+        "The fee cannot be greater than {}",
+        MAX_CONTRACT_FEE
+    );
+}
+
+pub(crate) fn assert_valid_payment_period(payment_period: u64) {
+    assert!(
+        (MIN_PAYMENT_PERIOD..=MAX_PAYMENT_PERIOD).contains(&payment_period),
+        "The payment period must be between {} and {}",
+        MIN_PAYMENT_PERIOD,
+        MAX_PAYMENT_PERIOD
+    );
+}
+
+// The new values are only applied to the loans listed afterwards,
+// every loan keeps the interest, fee and period it was listed with
+#[near_bindgen]
+impl NFTLoans {
+    pub fn set_contract_interest(&mut self, contract_interest: u64) -> u64 {
+        self.assert_role(Role::Admin);
+        assert_valid_interest(contract_interest);
+        self.contract_interest = contract_interest;
+        log_event(
+            "config_updated",
+            json!({ "contract_interest": contract_interest }),
+        );
+        self.contract_interest
+    }
+
+    pub fn set_contract_fee(&mut self, contract_fee: u64) -> u64 {
+        self.assert_role(Role::FeeManager);
+        assert_valid_fee(contract_fee);
+        self.contract_fee = contract_fee;
+        log_event("config_updated", json!({ "contract_fee": contract_fee }));
+        self.contract_fee
+    }
+
+    pub fn set_payment_period(&mut self, payment_period: u64) -> u64 {
+        self.assert_role(Role::Admin);
+        assert_valid_payment_period(payment_period);
+        self.payment_period = payment_period;
+        log_event(
+            "config_updated",
+            json!({ "payment_period": payment_period }),
+        );
+        self.payment_period
+    }
+
+    pub fn set_treasury_account(&mut self, treasury_account_id: AccountId) -> AccountId {
+        self.assert_role(Role::FeeManager);
+        self.treasury_account_id = treasury_account_id;
+        log_event(
+            "config_updated",
+            json!({ "treasury_account_id": self.treasury_account_id }),
+        );
+        self.treasury_account_id.clone()
+    }
+
+    pub fn set_ntv_token_contract(&mut self, ntv_token_contract: AccountId) -> AccountId {
+        self.assert_role(Role::Admin);
+        self.ntv_token_contract = ntv_token_contract;
+        log_event(
+            "config_updated",
+            json!({ "ntv_token_contract": self.ntv_token_contract }),
+        );
+        self.ntv_token_contract.clone()
+    }
+
+    pub fn get_config(&self) -> Config {
+        Config {
+            owner_account_id: self.owner_account_id.clone(),
+            treasury_account_id: self.treasury_account_id.clone(),
+            contract_interest: self.contract_interest,
+            contract_fee: self.contract_fee,
+            payment_period: self.payment_period,
+            is_minting_ntv: self.is_minting_ntv,
+            ntv_lender_multiply: self.ntv_lender_multiply,
+            ntv_borrower_multiply: self.ntv_borrower_multiply,
+            ntv_reward_basis: self.ntv_reward_basis,
+            ntv_reward_rules: self.ntv_reward_rules.clone(),
+            ntv_token_contract: self.ntv_token_contract.clone(),
+            ntv_vesting_period: self.ntv_vesting_period,
+            liquidation_grace_period: self.liquidation_grace_period,
+            liquidation_bounty: U128(self.liquidation_bounty),
+            default_listing_ttl: self.default_listing_ttl,
+        }
+    }
+}
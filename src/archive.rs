@@ -0,0 +1,101 @@
+use crate::*;
+
+/// Time a finished loan is kept by default before it can be pruned (30 days)
+pub const DEFAULT_LOAN_RETENTION_PERIOD: u64 = 1_000_000_000 * 60 * 60 * 24 * 30;
+
+#[near_bindgen]
+impl NFTLoans {
+    //move the finished loans older than the retention period to the archive and free their storage
+    //the loans of the status are scanned using pagination, the next page starts at the returned next_index
+    pub fn prune_loans(&mut self, status: LoanStatus, from_index: Option<U128>, limit: Option<u64>) -> PruneResult {
+        self.assert_role(Role::Admin);
+        self.assert_loans_migrated();
+        assert!(!status.is_active(), "Only the finished loans can be pruned");
+
+        let now = env::block_timestamp();
+        let limit = limit.unwrap_or(50);
+        let mut index = u128::from(from_index.unwrap_or(U128(0))) as u64;
+        let mut scanned: u64 = 0;
+        let mut pruned: u64 = 0;
+        while scanned < limit {
+            //the set is read again because pruning a loan moves the last loan of the set to its position
+            let loan_id = match self
+                .loans_by_status
+                .get(&status)
+                .and_then(|loans_set| loans_set.as_vector().get(index))
+            {
+                Some(loan_id) => loan_id,
+                None => break,
+            };
+            scanned += 1;
+            let loan = self.loans_by_id.get(&loan_id).unwrap();
+            let closed_time = loan.closed_time.unwrap_or(0);
+            if closed_time + self.loan_retention_period > now {
+                index += 1;
+                continue;
+            }
+            self.internal_archive_loan(&loan_id, &loan);
+            pruned += 1;
+        }
+
+        if pruned > 0 {
+            log_event(
+                "loans_pruned",
+                json!({ "status": status, "count": pruned }),
+            );
+        }
+        PruneResult {
+            pruned,
+            next_index: U128(index as u128),
+        }
+    }
+
+    pub fn set_loan_retention_period(&mut self, loan_retention_period: u64) -> u64 {
+        self.assert_role(Role::Admin);
+        self.loan_retention_period = loan_retention_period;
+        log_event(
+            "config_updated",
+            json!({ "loan_retention_period": loan_retention_period }),
+        );
+        self.loan_retention_period
+    }
+
+    //what happened to a loan, even if it was already pruned
+    pub fn get_loan_history(&self, loan_id: LoanId) -> Option<LoanHistoryOutput> {
+        if let Some(loan) = self.loans_by_id.get(&loan_id) {
+            return Some(LoanHistoryOutput {
+                id: loan_id,
+                archived: false,
+                summary: LoanSummary::from(&loan),
+            });
+        }
+        self.archived_loans.get(&loan_id).map(|summary| LoanHistoryOutput {
+            id: loan_id,
+            archived: true,
+            summary,
+        })
+    }
+}
+
+impl NFTLoans {
+    //keep the summary of the loan and remove it from every collection
+    pub(crate) fn internal_archive_loan(&mut self, loan_id: &LoanId, loan: &Loan) {
+        self.archived_loans.insert(loan_id, &LoanSummary::from(loan));
+        self.loans_by_id.remove(loan_id);
+        self.internal_remove_loan_from_status(loan_id, loan);
+
+        //the loan may have been removed from the sets of the accounts when it finished
+        if let Some(loans_set) = self.loans_per_owner.get(&loan.nft_owner) {
+            if loans_set.contains(loan_id) {
+                self.internal_remove_loan_from_owner(&loan.nft_owner, loan_id);
+            }
+        }
+        if let Some(loaner_id) = &loan.loaner_id {
+            if let Some(loans_set) = self.loans_per_lender.get(loaner_id) {
+                if loans_set.contains(loan_id) {
+                    self.internal_remove_loan_from_lender(loaner_id, loan_id);
+                }
+            }
+        }
+    }
+}
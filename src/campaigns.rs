@@ -0,0 +1,147 @@
+use crate::*;
+
+/// Maximum campaigns running at the same time
+pub const MAX_ACTIVE_CAMPAIGNS: u64 = 10;
+
+#[near_bindgen]
+impl NFTLoans {
+    //create a campaign that rewards the payed loans with NTV until its budget is exhausted
+    pub fn create_campaign(
+        &mut self,
+        name: String,
+        start_time: u64,
+        end_time: u64,
+        budget: U128,
+        eligible_collections: Vec<AccountId>,
+        reward_rate: u64,
+    ) -> CampaignId {
+        self.assert_role(Role::Admin);
+        assert!(start_time < end_time, "The campaign should start before it ends");
+        assert!(end_time > env::block_timestamp(), "The campaign has already ended");
+        assert!(budget.0 > 0, "The budget should be greater than 0");
+        assert!(reward_rate > 0, "The reward rate should be greater than 0");
+        self.internal_prune_campaigns();
+        assert!(
+            self.active_campaigns.len() < MAX_ACTIVE_CAMPAIGNS,
+            "There are already {} campaigns running",
+            MAX_ACTIVE_CAMPAIGNS
+        );
+
+        let id: CampaignId = self.last_campaign_id;
+        let campaign = Campaign {
+            name,
+            start_time,
+            end_time,
+            budget,
+            distributed: U128(0),
+            eligible_collections,
+            reward_rate,
+        };
+        self.campaigns.insert(&id, &campaign);
+        self.active_campaigns.insert(&id);
+        self.last_campaign_id += 1;
+
+        log_event("campaign_created", json!({ "campaign_id": id, "campaign": campaign }));
+        id
+    }
+
+    //finish a campaign before its end time, a campaign already ended is only removed from the active ones
+    pub fn end_campaign(&mut self, campaign_id: CampaignId) {
+        self.assert_role(Role::Admin);
+        let mut campaign = self.campaigns.get(&campaign_id).expect("Campaign not found");
+        let now = env::block_timestamp();
+        assert!(
+            campaign.end_time > now || self.active_campaigns.contains(&campaign_id),
+            "The campaign has already ended"
+        );
+        if campaign.end_time > now {
+            campaign.end_time = now;
+            self.campaigns.insert(&campaign_id, &campaign);
+        }
+        self.active_campaigns.remove(&campaign_id);
+        log_event("campaign_ended", json!({ "campaign_id": campaign_id }));
+    }
+
+    pub fn get_campaign(&self, campaign_id: CampaignId) -> Option<CampaignOutput> {
+        self.campaigns
+            .get(&campaign_id)
+            .map(|campaign| campaign_output(campaign_id, campaign))
+    }
+
+    //Query for the campaigns using pagination
+    pub fn get_campaigns(&self, from_index: Option<U128>, limit: Option<u64>) -> Vec<CampaignOutput> {
+        let start = u128::from(from_index.unwrap_or(U128(0)));
+
+        self.campaigns
+            .iter()
+            .skip(start as usize)
+            .take(limit.unwrap_or(50) as usize)
+            .map(|(campaign_id, campaign)| campaign_output(campaign_id, campaign))
+            .collect()
+    }
+
+    pub fn get_active_campaigns(&self) -> Vec<CampaignOutput> {
+        self.active_campaigns
+            .iter()
+            .filter_map(|campaign_id| self.get_campaign(campaign_id))
+            .collect()
+    }
+}
+
+fn campaign_output(id: CampaignId, campaign: Campaign) -> CampaignOutput {
+    let now = env::block_timestamp();
+    CampaignOutput {
+        id,
+        remaining_budget: U128(campaign.remaining_budget()),
+        progress: ratio_bps(campaign.distributed.0, campaign.budget.0),
+        is_active: campaign.is_active(now),
+        campaign,
+    }
+}
+
+impl NFTLoans {
+    //remove the campaigns finished or without budget from the active ones
+    pub(crate) fn internal_prune_campaigns(&mut self) {
+        let now = env::block_timestamp();
+        let campaign_ids: Vec<CampaignId> = self.active_campaigns.to_vec();
+        for campaign_id in campaign_ids {
+            let campaign = self.campaigns.get(&campaign_id).unwrap();
+            if now >= campaign.end_time || campaign.remaining_budget() == 0 {
+                self.active_campaigns.remove(&campaign_id);
+            }
+        }
+    }
+
+    //reward both parties of a payed loan with the campaigns running for its collection
+    pub(crate) fn internal_reward_campaigns(&mut self, loan: &Loan, basis: u128) {
+        let now = env::block_timestamp();
+        let campaign_ids: Vec<CampaignId> = self.active_campaigns.to_vec();
+        for campaign_id in campaign_ids {
+            let mut campaign = self.campaigns.get(&campaign_id).unwrap();
+            //the campaigns finished or without budget stop rewarding
+            if now >= campaign.end_time || campaign.remaining_budget() == 0 {
+                self.active_campaigns.remove(&campaign_id);
+                continue;
+            }
+            if now < campaign.start_time || !campaign.is_eligible(&loan.nft_contract) {
+                continue;
+            }
+
+            let reward = reward_amount(basis, 1, campaign.reward_rate);
+            let mut recipients = vec![loan.nft_owner.clone()];
+            if let Some(loaner_id) = loan.loaner_id.clone() {
+                recipients.push(loaner_id);
+            }
+            for account_id in recipients {
+                let amount = min(reward, campaign.remaining_budget());
+                campaign.distributed.0 += self.internal_accrue_ntv(&account_id, amount);
+            }
+
+            if campaign.remaining_budget() == 0 {
+                self.active_campaigns.remove(&campaign_id);
+                log_event("campaign_exhausted", json!({ "campaign_id": campaign_id }));
+            }
+            self.campaigns.insert(&campaign_id, &campaign);
+        }
+    }
+}
@@ -0,0 +1,41 @@
+use crate::*;
+
+#[near_bindgen]
+impl NFTLoans {
+    //Query for the loaned loans with a deadline between two timestamps (both included), sorted by deadline
+    pub fn get_loans_expiring_between(
+        &self,
+        start: u64,
+        end: u64,
+        from_index: Option<U128>,
+        limit: Option<u64>,
+    ) -> Vec<LoanOutput> {
+        let start_index = u128::from(from_index.unwrap_or(U128(0)));
+        //iter_from excludes the key, so it starts after the last loan of the previous nanosecond
+        let keys: Box<dyn Iterator<Item = ((u64, LoanId), LoanId)>> = if start == 0 {
+            Box::new(self.loaned_by_deadline.iter())
+        } else {
+            Box::new(self.loaned_by_deadline.iter_from((start - 1, LoanId::MAX)))
+        };
+
+        keys.take_while(|((loan_deadline, _), _)| *loan_deadline <= end)
+            .skip(start_index as usize)
+            .take(limit.unwrap_or(50) as usize)
+            .filter_map(|(_, loan_id)| self.get_nft_loan(loan_id))
+            .collect()
+    }
+
+    //Query for the loaned loans past their deadline, the oldest first
+    pub fn get_overdue_loans(&self, from_index: Option<U128>, limit: Option<u64>) -> Vec<LoanOutput> {
+        let now = env::block_timestamp();
+        let start = u128::from(from_index.unwrap_or(U128(0)));
+
+        self.loaned_by_deadline
+            .iter()
+            .take_while(|((loan_deadline, _), _)| *loan_deadline < now)
+            .skip(start as usize)
+            .take(limit.unwrap_or(50) as usize)
+            .filter_map(|(_, loan_id)| self.get_nft_loan(loan_id))
+            .collect()
+    }
+}
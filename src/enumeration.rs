@@ -4,12 +4,13 @@ use crate::*;
 impl NFTLoans {
 
     //get the information for a specific token ID
-    fn get_nft_loan(&self, loan_id: LoanId) -> Option<LoanOutput> {
+    pub(crate) fn get_nft_loan(&self, loan_id: LoanId) -> Option<LoanOutput> {
         //if there is some loan ID in the loans_by_id collection
-        if let loans = self.loans_by_id.get(&loan_id).unwrap() {
+        if let Some(loans) = self.loans_by_id.get(&loan_id) {
             //we'll return the data for that loan
             Some(LoanOutput {
                 id:loan_id,
+                listing_expired:loans.is_listing_expired(env::block_timestamp()),
                 loan:loans.into(),
             })
         } else { //if there wasn't a loan ID in the loans_by_id collection, we return None
@@ -54,6 +55,71 @@ impl NFTLoans {
     //         .collect()
     // }
 
+    //get the active loan that holds an NFT as collateral
+    pub fn get_loan_by_nft(&self, nft_contract: AccountId, nft_id: String) -> Option<LoanOutput> {
+        self.loan_by_nft
+            .get(&nft_key(&nft_contract, &nft_id))
+            .and_then(|loan_id| self.get_nft_loan(loan_id))
+    }
+
+    pub fn is_nft_collateralized(&self, nft_contract: AccountId, nft_id: String) -> bool {
+        self.loan_by_nft.contains_key(&nft_key(&nft_contract, &nft_id))
+    }
+
+    //get the number of loans with a status
+    pub fn loan_supply_by_status(&self, status: LoanStatus) -> U128 {
+        self.loans_by_status
+            .get(&status)
+            .map(|loans_set| U128(loans_set.len() as u128))
+            .unwrap_or(U128(0))
+    }
+
+    //Query for the loans with a status using pagination
+    pub fn get_loans_by_status(
+        &self,
+        status: LoanStatus,
+        from_index: Option<U128>,
+        limit: Option<u64>,
+    ) -> Vec<LoanOutput> {
+        let loans = if let Some(loans_set) = self.loans_by_status.get(&status) {
+            loans_set
+        } else {
+            return vec![];
+        };
+
+        let start = u128::from(from_index.unwrap_or(U128(0)));
+
+        loans.iter()
+            .skip(start as usize)
+            .take(limit.unwrap_or(50) as usize)
+            .map(|loan_id| self.get_nft_loan(loan_id).unwrap())
+            .collect()
+    }
+
+    //Query for the loans of a collection with a status using pagination
+    pub fn get_loans_by_status_and_collection(
+        &self,
+        status: LoanStatus,
+        nft_contract: AccountId,
+        from_index: Option<U128>,
+        limit: Option<u64>,
+    ) -> Vec<LoanOutput> {
+        let key = collection_status_key(&nft_contract, &status);
+        let loans = if let Some(loans_set) = self.loans_by_collection_status.get(&key) {
+            loans_set
+        } else {
+            return vec![];
+        };
+
+        let start = u128::from(from_index.unwrap_or(U128(0)));
+
+        loans.iter()
+            .skip(start as usize)
+            .take(limit.unwrap_or(50) as usize)
+            .map(|loan_id| self.get_nft_loan(loan_id).unwrap())
+            .collect()
+    }
+
     //View the loan_id of the last loan
     pub fn get_contract_interest(&self)-> u64 {
         self.contract_interest
@@ -172,13 +238,40 @@ impl NFTLoans {
             
             ntv_status:self.is_minting_ntv,
 
-            ntv_multiply: self.ntv_multiply,
+            ntv_lender_multiply: self.ntv_lender_multiply,
+
+            ntv_borrower_multiply: self.ntv_borrower_multiply,
             
             total_loans_active: self.loans_active,
             
             total_amount_lent: self.total_amount_lent,
             
             loan_current_ath: self.loan_current_ath,
+
+            loan_ath_id: self.loan_ath_id,
+
+            status_counts: self.status_counts.clone(),
+
+            total_outstanding_principal: self.total_outstanding_principal,
+
+            total_interest_paid: self.total_interest_paid,
+
+            total_defaulted_amount: self.total_defaulted_amount,
+
+            default_rate: ratio_bps(
+                self.status_counts.expired as u128,
+                (self.status_counts.expired + self.status_counts.payed) as u128,
+            ),
+
+            total_fees_collected: self.internal_get_treasury(NEAR_CURRENCY).total_collected.0,
+
+            treasury_balance: self.internal_get_treasury(NEAR_CURRENCY).balance.0,
+
+            ntv_total_emitted: self.ntv_total_emitted,
+
+            ntv_total_minted: self.ntv_total_minted,
+
+            ntv_remaining_allowance: self.internal_remaining_allowance(),
        };
        metrics
     }
@@ -0,0 +1,19 @@
+use crate::*;
+
+/// Standard name used in the events logged by Nativo Loans
+pub const EVENT_STANDARD: &str = "nativo_loans";
+/// Version of the events format
+pub const EVENT_VERSION: &str = "1.0.0";
+
+//log an event following the NEP-297 format so indexers can follow the contract changes
+pub(crate) fn log_event(event: &str, data: near_sdk::serde_json::Value) {
+    env::log_str(&format!(
+        "EVENT_JSON:{}",
+        json!({
+            "standard": EVENT_STANDARD,
+            "version": EVENT_VERSION,
+            "event": event,
+            "data": [data],
+        })
+    ));
+}
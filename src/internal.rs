@@ -11,6 +11,18 @@ pub(crate) fn hash_account_id(account_id: &AccountId) -> CryptoHash {
     hash
 }
 
+//used to generate a unique prefix for the collections of an index key
+pub(crate) fn hash_key(key: &str) -> CryptoHash {
+    let mut hash = CryptoHash::default();
+    hash.copy_from_slice(&env::sha256(key.as_bytes()));
+    hash
+}
+
+//used to identify an NFT of any contract in the indexes
+pub(crate) fn nft_key(nft_contract: &AccountId, nft_id: &str) -> String {
+    format!("{}:{}", nft_contract, nft_id)
+}
+
 impl NFTLoans {
     //add a loan to the set of tokens an owner has
     pub(crate) fn internal_add_loan_to_owner(
@@ -112,4 +124,125 @@ impl NFTLoans {
             self.loans_per_lender.insert(account_id, &loans_set);
         }
     }
-}
\ No newline at end of file
+}
+//add a loan to the set of an index, creating the set with the given prefix if it doesn't exist
+pub(crate) fn internal_add_to_index<K: BorshSerialize + BorshDeserialize>(
+    index: &mut LookupMap<K, UnorderedSet<LoanId>>,
+    key: &K,
+    prefix: StorageKey,
+    loan_id: &LoanId,
+) {
+    let mut loans_set = index
+        .get(key)
+        .unwrap_or_else(|| UnorderedSet::new(prefix.try_to_vec().unwrap()));
+    loans_set.insert(loan_id);
+    index.insert(key, &loans_set);
+}
+
+//remove a loan from the set of an index, the set is removed when it gets empty
+pub(crate) fn internal_remove_from_index<K: BorshSerialize + BorshDeserialize>(
+    index: &mut LookupMap<K, UnorderedSet<LoanId>>,
+    key: &K,
+    loan_id: &LoanId,
+) {
+    if let Some(mut loans_set) = index.get(key) {
+        loans_set.remove(loan_id);
+        if loans_set.is_empty() {
+            index.remove(key);
+        } else {
+            index.insert(key, &loans_set);
+        }
+    }
+}
+
+//key of the index of the loans by collection and status
+pub(crate) fn collection_status_key(nft_contract: &AccountId, status: &LoanStatus) -> String {
+    format!("{}:{:?}", nft_contract, status)
+}
+
+impl NFTLoans {
+    //add a loan to the indexes of its current status
+    pub(crate) fn internal_add_loan_to_status(&mut self, loan_id: &LoanId, loan: &Loan) {
+        internal_add_to_index(
+            &mut self.loans_by_status,
+            &loan.status,
+            StorageKey::LoansByStatusInner {
+                status: loan.status.clone(),
+            },
+            loan_id,
+        );
+        let key = collection_status_key(&loan.nft_contract, &loan.status);
+        internal_add_to_index(
+            &mut self.loans_by_collection_status,
+            &key,
+            StorageKey::LoansByCollectionStatusInner {
+                key_hash: hash_key(&key),
+            },
+            loan_id,
+        );
+    }
+
+    //remove a loan from the indexes of its current status
+    pub(crate) fn internal_remove_loan_from_status(&mut self, loan_id: &LoanId, loan: &Loan) {
+        internal_remove_from_index(&mut self.loans_by_status, &loan.status, loan_id);
+        let key = collection_status_key(&loan.nft_contract, &loan.status);
+        internal_remove_from_index(&mut self.loans_by_collection_status, &key, loan_id);
+    }
+
+    //every change of status goes through here so the indexes are kept up to date
+    pub(crate) fn internal_update_status(&mut self, loan_id: &LoanId, loan: &mut Loan, status: LoanStatus) {
+        self.internal_remove_loan_from_status(loan_id, loan);
+        let previous_status = std::mem::replace(&mut loan.status, status);
+        self.internal_add_loan_to_status(loan_id, loan);
+        if previous_status == LoanStatus::Pending && loan.status != LoanStatus::Pending {
+            self.internal_remove_loan_from_pending_indexes(loan_id, loan);
+        }
+        if let Some(loan_deadline) = loan.loan_deadline {
+            if previous_status == LoanStatus::Loaned {
+                self.loaned_by_deadline.remove(&(loan_deadline, *loan_id));
+            }
+            if loan.status == LoanStatus::Loaned {
+                self.loaned_by_deadline.insert(&(loan_deadline, *loan_id), loan_id);
+            }
+        }
+        self.internal_record_account_stats(loan);
+        self.internal_record_protocol_metrics(loan, &previous_status);
+        //the NFT stops being collateral once the loan finishes
+        if !loan.status.is_active() {
+            loan.closed_time = Some(env::block_timestamp());
+            let key = nft_key(&loan.nft_contract, &loan.nft_id);
+            if self.loan_by_nft.get(&key) == Some(*loan_id) {
+                self.loan_by_nft.remove(&key);
+            }
+        }
+    }
+
+    //fund a pending loan with the principal held by the contract
+    //used by loan_for_nft with the deposit of the lender and by the offers with their escrow
+    pub(crate) fn internal_fund_loan(&mut self, loan_id: &LoanId, loan: &mut Loan, loaner_id: AccountId) {
+        let amount = loan.loan_requested;
+        self.total_amount_lent += amount;
+        if amount > self.loan_current_ath {
+            self.loan_current_ath = amount;
+            self.loan_ath_id = Some(*loan_id);
+        }
+        self.loans_active += 1;
+
+        loan.loaner_id = Some(loaner_id.clone());
+        loan.loan_time = Some(env::block_timestamp());
+        loan.loan_block = Some(env::block_height());
+        loan.loan_deadline = Some(env::block_timestamp() + loan.loan_period);
+        self.internal_update_status(loan_id, loan, LoanStatus::Loaned);
+
+        //Here is removed % fee from amount transfered to owner and sended to the treasury
+        //the fee rounds up and both amounts add up to the principal
+        let (amount_to_owner, amount_to_treasury) = split_fee(amount, loan.loan_fee);
+        self.internal_accrue_fee(NEAR_CURRENCY, amount_to_treasury);
+
+        //the fee stays in the contract until it is withdrawn to the treasury
+        Promise::new(loan.nft_owner.clone()).transfer(amount_to_owner);
+
+        self.loans_by_id.insert(loan_id, loan);
+        self.internal_add_loan_to_lender(&loaner_id, loan_id);
+    }
+}
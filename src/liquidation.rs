@@ -0,0 +1,89 @@
+use crate::*;
+
+/// Time after the deadline before anyone can liquidate a loan (1 day)
+pub const DEFAULT_LIQUIDATION_GRACE_PERIOD: u64 = 1_000_000_000 * 60 * 60 * 24;
+/// Bounty paid from the treasury to the account that liquidates a loan (0.01 NEAR)
+pub const DEFAULT_LIQUIDATION_BOUNTY: u128 = 10_000_000_000_000_000_000_000;
+/// Gas for the transfer of the collateral to the lender
+pub const GAS_FOR_NFT_TRANSFER: Gas = Gas(5_000_000_000_000);
+
+#[near_bindgen]
+impl NFTLoans {
+    //anyone can send the collateral of an expired loan to the lender once the grace period passed
+    //the caller receives the bounty from the treasury, capped to its balance, and it returns the amount paid
+    pub fn liquidate(&mut self, loan_id: LoanId) -> U128 {
+        self.assert_not_paused(Operation::Liquidation);
+        let mut loan: Loan = self.loans_by_id.get(&loan_id).expect("The loan does not exist");
+        assert!(loan.status == LoanStatus::Loaned, "Only a loaned NFT can be liquidated");
+        let loan_deadline = loan.loan_deadline.unwrap();
+        assert!(
+            env::block_timestamp() >= loan_deadline + self.liquidation_grace_period,
+            "The grace period of the loan has not expired"
+        );
+
+        let keeper_id = env::predecessor_account_id();
+        self.internal_liquidate_loan(&loan_id, &mut loan);
+
+        let mut treasury = self.internal_get_treasury(NEAR_CURRENCY);
+        let bounty = min(self.liquidation_bounty, treasury.balance.0);
+        if bounty > 0 {
+            treasury.balance.0 -= bounty;
+            treasury.total_withdrawn.0 += bounty;
+            self.treasury.insert(&NEAR_CURRENCY.to_string(), &treasury);
+            Promise::new(keeper_id.clone()).transfer(bounty);
+        }
+
+        log_event(
+            "loan_liquidated",
+            json!({
+                "loan_id": loan_id,
+                "loaner_id": loan.loaner_id,
+                "keeper_id": keeper_id,
+                "bounty": U128(bounty),
+            }),
+        );
+        U128(bounty)
+    }
+
+    pub fn set_liquidation_grace_period(&mut self, liquidation_grace_period: u64) -> u64 {
+        self.assert_role(Role::Admin);
+        self.liquidation_grace_period = liquidation_grace_period;
+        log_event(
+            "config_updated",
+            json!({ "liquidation_grace_period": liquidation_grace_period }),
+        );
+        self.liquidation_grace_period
+    }
+
+    pub fn set_liquidation_bounty(&mut self, liquidation_bounty: U128) -> U128 {
+        self.assert_role(Role::FeeManager);
+        self.liquidation_bounty = liquidation_bounty.into();
+        log_event(
+            "config_updated",
+            json!({ "liquidation_bounty": liquidation_bounty }),
+        );
+        liquidation_bounty
+    }
+}
+
+impl NFTLoans {
+    //expire the loan and send the collateral to the lender
+    //shared by the lender withdraw and the permissionless liquidation
+    pub(crate) fn internal_liquidate_loan(&mut self, loan_id: &LoanId, loan: &mut Loan) {
+        let loaner_id = loan.loaner_id.clone().unwrap();
+        self.loans_active -= 1;
+        self.internal_update_status(loan_id, loan, LoanStatus::Expired);
+        self.loans_by_id.insert(loan_id, loan);
+        self.internal_remove_loan_from_owner(&loan.nft_owner, loan_id);
+        self.internal_remove_loan_from_lender(&loaner_id, loan_id);
+
+        ext_contract_nft::nft_transfer(
+            loaner_id,
+            loan.nft_id.to_string(),
+            "Withdraw of NFT from Nativo Loans".to_string(),
+            loan.nft_contract.clone(),
+            1,
+            GAS_FOR_NFT_TRANSFER,
+        );
+    }
+}
@@ -0,0 +1,105 @@
+use crate::*;
+
+#[near_bindgen]
+impl NFTLoans {
+    //the borrower can change the amount, description and payment period of a pending loan
+    //the payback is calculated again with the interest of the loan and the version is increased
+    pub fn update_loan_request(
+        &mut self,
+        loan_id: LoanId,
+        loan_amount_requested: Option<U128>,
+        description: Option<String>,
+        loan_period: Option<u64>,
+    ) -> LoanOutput {
+        self.assert_not_paused(Operation::Listing);
+        let mut loan: Loan = self.loans_by_id.get(&loan_id).expect("The loan does not exist");
+        assert!(loan.status == LoanStatus::Pending, "Only a pending loan can be updated");
+        assert!(
+            !loan.is_listing_expired(env::block_timestamp()),
+            "The listing of the loan expired"
+        );
+        assert!(
+            env::predecessor_account_id() == loan.nft_owner,
+            "You are not the owner of this NFT"
+        );
+
+        //the sorted indexes use the old values as key
+        self.internal_remove_loan_from_pending_indexes(&loan_id, &loan);
+        if let Some(loan_amount_requested) = loan_amount_requested {
+            let loan_amount_requested = u128::from(loan_amount_requested);
+            assert!(loan_amount_requested > 0, "The amount should be greater than 0");
+            loan.loan_requested = loan_amount_requested;
+            loan.loan_payback = payback_amount(loan_amount_requested, loan.loan_interest);
+        }
+        if description.is_some() {
+            loan.description = description;
+        }
+        if let Some(loan_period) = loan_period {
+            assert_valid_payment_period(loan_period);
+            loan.loan_period = loan_period;
+        }
+        loan.version += 1;
+        self.internal_add_loan_to_pending_indexes(&loan_id, &loan);
+        self.loans_by_id.insert(&loan_id, &loan);
+
+        log_event(
+            "loan_request_updated",
+            json!({
+                "loan_id": loan_id,
+                "version": loan.version,
+                "loan_requested": U128(loan.loan_requested),
+                "loan_payback": U128(loan.loan_payback),
+                "loan_period": loan.loan_period,
+            }),
+        );
+        LoanOutput {
+            id: loan_id,
+            loan,
+            listing_expired: false,
+        }
+    }
+
+    //anyone can return the NFT of an expired listing to its owner
+    pub fn return_expired_listing(&mut self, loan_id: LoanId) {
+        let mut loan: Loan = self.loans_by_id.get(&loan_id).expect("The loan does not exist");
+        assert!(
+            loan.is_listing_expired(env::block_timestamp()),
+            "The listing of the loan has not expired"
+        );
+
+        self.internal_update_status(&loan_id, &mut loan, LoanStatus::ListingExpired);
+        self.loans_by_id.insert(&loan_id, &loan);
+        self.internal_remove_loan_from_owner(&loan.nft_owner, &loan_id);
+
+        ext_contract_nft::nft_transfer(
+            loan.nft_owner.clone(),
+            loan.nft_id.to_string(),
+            "Listing of Nativo Loans expired".to_string(),
+            loan.nft_contract.clone(),
+            1,
+            GAS_FOR_NFT_TRANSFER,
+        );
+        log_event(
+            "listing_expired",
+            json!({
+                "loan_id": loan_id,
+                "nft_owner": loan.nft_owner,
+                "caller_id": env::predecessor_account_id(),
+            }),
+        );
+    }
+
+    //None to list the loans without expiry when the message has no ttl
+    pub fn set_default_listing_ttl(&mut self, default_listing_ttl: Option<u64>) -> Option<u64> {
+        self.assert_role(Role::Admin);
+        if let Some(listing_ttl) = default_listing_ttl {
+            assert!(listing_ttl > 0, "The listing ttl should be greater than 0");
+        }
+        self.default_listing_ttl = default_listing_ttl;
+        log_event(
+            "config_updated",
+            json!({ "default_listing_ttl": default_listing_ttl }),
+        );
+        self.default_listing_ttl
+    }
+}
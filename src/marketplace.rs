@@ -0,0 +1,106 @@
+use crate::*;
+
+/// Pending loans scanned at most by a query, so a rare collection cannot exhaust the gas
+pub const MAX_PENDING_SCAN: usize = 500;
+
+const ALL_SORT_FIELDS: [SortBy; 4] = [
+    SortBy::Amount,
+    SortBy::Interest,
+    SortBy::Duration,
+    SortBy::ListingTime,
+];
+
+#[near_bindgen]
+impl NFTLoans {
+    //Query for the pending loans sorted by a field using a cursor for the pagination
+    //the collection filter skips the loans of other collections, at most MAX_PENDING_SCAN loans are scanned
+    //so a page can have fewer loans than the limit and still return a cursor to continue
+    pub fn get_pending_loans_sorted(
+        &self,
+        sort_by: SortBy,
+        order: Option<SortOrder>,
+        cursor: Option<SortCursor>,
+        limit: Option<u64>,
+        nft_contract: Option<AccountId>,
+    ) -> LoansPage {
+        let index = self.pending_index(sort_by);
+        let limit = limit.unwrap_or(50) as usize;
+        let cursor_key = cursor.map(|cursor| (cursor.value.0, cursor.loan_id));
+        let now = env::block_timestamp();
+
+        let keys: Box<dyn Iterator<Item = ((u128, LoanId), LoanId)>> =
+            match (order.unwrap_or(SortOrder::Ascending), cursor_key) {
+                (SortOrder::Ascending, None) => Box::new(index.iter()),
+                (SortOrder::Ascending, Some(key)) => Box::new(index.iter_from(key)),
+                (SortOrder::Descending, None) => Box::new(index.iter_rev()),
+                (SortOrder::Descending, Some(key)) => Box::new(index.iter_rev_from(key)),
+            };
+
+        let mut loans: Vec<LoanOutput> = vec![];
+        let mut last_key = None;
+        let mut scanned = 0;
+        for (key, loan_id) in keys {
+            if loans.len() == limit || scanned == MAX_PENDING_SCAN {
+                break;
+            }
+            scanned += 1;
+            last_key = Some(key);
+            let loan = match self.loans_by_id.get(&loan_id) {
+                Some(loan) => loan,
+                None => continue,
+            };
+            if let Some(nft_contract) = &nft_contract {
+                if loan.nft_contract != *nft_contract {
+                    continue;
+                }
+            }
+            let listing_expired = loan.is_listing_expired(now);
+            loans.push(LoanOutput { id: loan_id, loan, listing_expired });
+        }
+
+        //a full page or a scan that stopped at the bound may have more loans after it
+        let next_cursor = if loans.len() == limit || scanned == MAX_PENDING_SCAN {
+            last_key.map(|(value, loan_id)| SortCursor {
+                value: U128(value),
+                loan_id,
+            })
+        } else {
+            None
+        };
+        LoansPage { loans, next_cursor }
+    }
+}
+
+impl NFTLoans {
+    fn pending_index(&self, sort_by: SortBy) -> &TreeMap<(u128, LoanId), LoanId> {
+        match sort_by {
+            SortBy::Amount => &self.pending_by_amount,
+            SortBy::Interest => &self.pending_by_interest,
+            SortBy::Duration => &self.pending_by_duration,
+            SortBy::ListingTime => &self.pending_by_listing_time,
+        }
+    }
+
+    fn pending_index_mut(&mut self, sort_by: SortBy) -> &mut TreeMap<(u128, LoanId), LoanId> {
+        match sort_by {
+            SortBy::Amount => &mut self.pending_by_amount,
+            SortBy::Interest => &mut self.pending_by_interest,
+            SortBy::Duration => &mut self.pending_by_duration,
+            SortBy::ListingTime => &mut self.pending_by_listing_time,
+        }
+    }
+
+    pub(crate) fn internal_add_loan_to_pending_indexes(&mut self, loan_id: &LoanId, loan: &Loan) {
+        for sort_by in ALL_SORT_FIELDS {
+            let key = (loan.sort_value(sort_by), *loan_id);
+            self.pending_index_mut(sort_by).insert(&key, loan_id);
+        }
+    }
+
+    pub(crate) fn internal_remove_loan_from_pending_indexes(&mut self, loan_id: &LoanId, loan: &Loan) {
+        for sort_by in ALL_SORT_FIELDS {
+            let key = (loan.sort_value(sort_by), *loan_id);
+            self.pending_index_mut(sort_by).remove(&key);
+        }
+    }
+}
@@ -0,0 +1,217 @@
+//! Fixed-point math of the loans on U256 so the intermediate products cannot overflow.
+//!
+//! Rounding rules:
+//! - interest rounds up, the lender never receives less than the agreed rate
+//! - fees round up in favor of the treasury, but never exceed the amount they are taken from
+//! - the amount sent to the owner is the deposit minus the fee, so payouts never exceed deposits
+//! - pro-rata amounts, rewards and percentages round down
+use crate::U256;
+
+/// 10000=100%
+pub const BASIS_POINTS: u64 = 10_000;
+
+/// Direction used to round the result of a division
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Rounding {
+    Down,
+    Up,
+}
+
+/// a * b / denominator, None if the denominator is 0 or the result does not fit in u128
+pub fn checked_mul_div(a: u128, b: u128, denominator: u128, rounding: Rounding) -> Option<u128> {
+    if denominator == 0 {
+        return None;
+    }
+    let product = U256::from(a) * U256::from(b);
+    let denominator = U256::from(denominator);
+    let mut result = product / denominator;
+    if rounding == Rounding::Up && !(product % denominator).is_zero() {
+        result += U256::one();
+    }
+    if result > U256::from(u128::MAX) {
+        return None;
+    }
+    Some(result.as_u128())
+}
+
+/// a * b / denominator, panics if the denominator is 0 or the result does not fit in u128
+pub fn mul_div(a: u128, b: u128, denominator: u128, rounding: Rounding) -> u128 {
+    checked_mul_div(a, b, denominator, rounding).expect("Math overflow")
+}
+
+/// Part of an amount given in basis points
+pub fn apply_bps(amount: u128, bps: u64, rounding: Rounding) -> u128 {
+    mul_div(amount, bps as u128, BASIS_POINTS as u128, rounding)
+}
+
+/// Interest of a loan, rounded up
+pub fn interest_amount(principal: u128, interest: u64) -> u128 {
+    apply_bps(principal, interest, Rounding::Up)
+}
+
+/// Amount the borrower has to pay back, principal plus interest
+pub fn payback_amount(principal: u128, interest: u64) -> u128 {
+    principal
+        .checked_add(interest_amount(principal, interest))
+        .expect("Math overflow")
+}
+
+/// Fee taken from an amount, rounded up in favor of the treasury and never above the amount
+pub fn fee_amount(amount: u128, fee: u64) -> u128 {
+    checked_mul_div(amount, fee as u128, BASIS_POINTS as u128, Rounding::Up)
+        .map_or(amount, |fee| std::cmp::min(fee, amount))
+}
+
+/// Splits a deposit between the owner and the treasury, both parts always add up to the deposit
+pub fn split_fee(amount: u128, fee: u64) -> (u128, u128) {
+    let to_treasury = fee_amount(amount, fee);
+    (amount - to_treasury, to_treasury)
+}
+
+/// Part of an amount for the time elapsed over a period, rounded down and never above the amount
+pub fn pro_rata(amount: u128, elapsed: u64, period: u64) -> u128 {
+    if period == 0 || elapsed >= period {
+        return amount;
+    }
+    mul_div(amount, elapsed as u128, period as u128, Rounding::Down)
+}
+
+/// NTV reward for a basis, a multiplier and a boost in basis points, rounded down and saturated
+pub fn reward_amount(basis: u128, multiply: u128, boost: u64) -> u128 {
+    //a product above U256 is far above u128 once divided, so it saturates too
+    U256::from(basis)
+        .checked_mul(U256::from(multiply))
+        .and_then(|product| product.checked_mul(U256::from(boost)))
+        .map(|product| product / U256::from(BASIS_POINTS))
+        .map_or(u128::MAX, |reward| std::cmp::min(reward, U256::from(u128::MAX)).as_u128())
+}
+
+/// Part of the total in basis points, rounded down
+pub fn ratio_bps(part: u128, total: u128) -> u64 {
+    if total == 0 {
+        return 0;
+    }
+    mul_div(part, BASIS_POINTS as u128, total, Rounding::Down) as u64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MAX: u128 = u128::MAX;
+
+    #[test]
+    fn mul_div_rounds_in_the_given_direction() {
+        assert_eq!(mul_div(10, 1, 3, Rounding::Down), 3);
+        assert_eq!(mul_div(10, 1, 3, Rounding::Up), 4);
+        assert_eq!(mul_div(9, 1, 3, Rounding::Up), 3);
+        assert_eq!(mul_div(0, 5, 3, Rounding::Up), 0);
+    }
+
+    #[test]
+    fn mul_div_does_not_overflow_on_the_intermediate_product() {
+        assert_eq!(mul_div(MAX, MAX, MAX, Rounding::Down), MAX);
+        assert_eq!(mul_div(MAX, MAX, MAX, Rounding::Up), MAX);
+        assert_eq!(mul_div(MAX, 2, 2, Rounding::Down), MAX);
+        assert_eq!(mul_div(MAX, MAX - 1, MAX, Rounding::Down), MAX - 1);
+        assert_eq!(mul_div(MAX - 1, MAX, MAX, Rounding::Up), MAX - 1);
+    }
+
+    #[test]
+    fn checked_mul_div_rejects_results_above_u128() {
+        assert_eq!(checked_mul_div(MAX, 2, 1, Rounding::Down), None);
+        assert_eq!(checked_mul_div(MAX, MAX, MAX - 1, Rounding::Down), None);
+        assert_eq!(checked_mul_div(1, 1, 0, Rounding::Down), None);
+        //rounding up the maximum does not wrap around
+        assert_eq!(checked_mul_div(MAX, 3, 3, Rounding::Up), Some(MAX));
+    }
+
+    #[test]
+    #[should_panic(expected = "Math overflow")]
+    fn mul_div_panics_on_overflow() {
+        mul_div(MAX, 3, 2, Rounding::Down);
+    }
+
+    #[test]
+    fn interest_rounds_up_for_the_lender() {
+        assert_eq!(interest_amount(1_000, 800), 80);
+        assert_eq!(interest_amount(1, 800), 1);
+        assert_eq!(interest_amount(0, 800), 0);
+        assert_eq!(interest_amount(12_345, 0), 0);
+        assert_eq!(payback_amount(1_000, 800), 1_080);
+        assert_eq!(payback_amount(1, 1), 2);
+    }
+
+    #[test]
+    fn interest_near_u128_max() {
+        let principal = MAX / 2;
+        assert_eq!(interest_amount(principal, 10_000), principal);
+        assert_eq!(payback_amount(principal, 10_000), MAX - 1);
+        assert_eq!(interest_amount(MAX, 5_000), MAX / 2 + 1);
+    }
+
+    #[test]
+    #[should_panic(expected = "Math overflow")]
+    fn payback_panics_when_it_does_not_fit_in_u128() {
+        payback_amount(MAX, 1);
+    }
+
+    #[test]
+    fn fee_rounds_up_for_the_treasury() {
+        assert_eq!(fee_amount(1_000, 200), 20);
+        assert_eq!(fee_amount(1, 200), 1);
+        assert_eq!(fee_amount(49, 200), 1);
+        assert_eq!(fee_amount(51, 200), 2);
+        assert_eq!(fee_amount(0, 200), 0);
+        assert_eq!(fee_amount(1_000, 0), 0);
+    }
+
+    #[test]
+    fn fee_never_exceeds_the_amount() {
+        assert_eq!(fee_amount(1, 10_000), 1);
+        assert_eq!(fee_amount(MAX, 10_000), MAX);
+        assert_eq!(fee_amount(MAX, 20_000), MAX);
+    }
+
+    #[test]
+    fn split_fee_adds_up_to_the_deposit() {
+        for amount in [0, 1, 2, 49, 51, 999, 1_000_000_000_000_000_000_000_000, MAX - 1, MAX] {
+            for fee in [0, 1, 200, 999, 1_000, 9_999, 10_000] {
+                let (to_owner, to_treasury) = split_fee(amount, fee);
+                assert_eq!(to_owner + to_treasury, amount);
+                assert!(to_treasury >= amount / 10_000 * fee as u128);
+            }
+        }
+        assert_eq!(split_fee(1_000, 200), (980, 20));
+        assert_eq!(split_fee(MAX, 10_000), (0, MAX));
+    }
+
+    #[test]
+    fn pro_rata_rounds_down_and_never_exceeds_the_amount() {
+        assert_eq!(pro_rata(10, 1, 3), 3);
+        assert_eq!(pro_rata(10, 3, 3), 10);
+        assert_eq!(pro_rata(10, 4, 3), 10);
+        assert_eq!(pro_rata(10, 0, 3), 0);
+        assert_eq!(pro_rata(10, 5, 0), 10);
+        assert_eq!(pro_rata(MAX, u64::MAX - 1, u64::MAX), MAX - MAX / u64::MAX as u128);
+        assert_eq!(pro_rata(MAX, 1, u64::MAX), MAX / u64::MAX as u128);
+    }
+
+    #[test]
+    fn reward_amount_saturates() {
+        assert_eq!(reward_amount(1_000, 3, 10_000), 3_000);
+        assert_eq!(reward_amount(1_000, 3, 15_000), 4_500);
+        assert_eq!(reward_amount(1, 1, 5_000), 0);
+        assert_eq!(reward_amount(MAX, MAX, 50_000), MAX);
+        assert_eq!(reward_amount(MAX, 1, 10_000), MAX);
+    }
+
+    #[test]
+    fn ratio_bps_rounds_down() {
+        assert_eq!(ratio_bps(1, 3), 3_333);
+        assert_eq!(ratio_bps(3, 3), 10_000);
+        assert_eq!(ratio_bps(0, 0), 0);
+        assert_eq!(ratio_bps(MAX, MAX), 10_000);
+        assert_eq!(ratio_bps(MAX / 2, MAX), 4_999);
+    }
+}
@@ -3,6 +3,8 @@ use crate::*;
 use std::mem::size_of;
 
 pub type LoanId = u64;
+pub type CampaignId = u64;
+pub type OfferId = u64;
 
 
 /// Status of a loan.
@@ -19,6 +21,15 @@ pub enum LoanStatus {
     Payed,
     // If no body loaned for this NFT. This status gets after owners claim back its NFT.
     Canceled,
+    /// No body loaned before the listing expired, the NFT was returned to its owner.
+    ListingExpired,
+}
+
+impl LoanStatus {
+    //the NFT is still held by the contract as collateral
+    pub fn is_active(&self) -> bool {
+        matches!(self, LoanStatus::Pending | LoanStatus::Loaned)
+    }
 }
 
 /// Proposal for loaning that are sent to this DAO.
@@ -49,6 +60,65 @@ pub struct Loan {
     pub loan_deadline: Option<EpochHeight>,
     /// When somebody loaned.
     pub loaner_id: Option<AccountId>,
+    /// Interest of the loan when it was listed, 10000=100%
+    pub loan_interest: u64,
+    /// Fee of the loan when it was listed, 10000=100%
+    pub loan_fee: u64,
+    /// Duration of the payment period when it was listed
+    pub loan_period: u64,
+    /// Block height when somebody loaned
+    pub loan_block: Option<u64>,
+    /// False if the NFT was relisted during the cooldown, the loan gets no NTV rewards
+    pub rewards_eligible: bool,
+    /// When the loan was payed, expired or canceled
+    pub closed_time: Option<EpochHeight>,
+    /// Increased every time the borrower edits the listing
+    pub version: u32,
+    /// After this time the loan cannot be funded, None if the listing never expires
+    pub listing_expires_at: Option<EpochHeight>,
+}
+
+/// Layout of the loans stored before the upgrade, they are converted by migrate_loans.
+#[derive(BorshSerialize, BorshDeserialize)]
+pub struct OldLoan {
+    pub nft_owner: AccountId,
+    pub nft_contract: AccountId,
+    pub nft_id: String,
+    pub description: Option<String>,
+    pub loan_requested: u128,
+    pub loan_payback: u128,
+    pub status: LoanStatus,
+    pub submission_time: EpochHeight,
+    pub loan_time: Option<EpochHeight>,
+    pub loan_deadline: Option<EpochHeight>,
+    pub loaner_id: Option<AccountId>,
+}
+
+impl OldLoan {
+    //the old loans did not keep their terms, they take the ones of the contract
+    pub fn into_loan(self, loan_interest: u64, loan_fee: u64, loan_period: u64) -> Loan {
+        Loan {
+            nft_owner: self.nft_owner,
+            nft_contract: self.nft_contract,
+            nft_id: self.nft_id,
+            description: self.description,
+            loan_requested: self.loan_requested,
+            loan_payback: self.loan_payback,
+            status: self.status,
+            submission_time: self.submission_time,
+            loan_time: self.loan_time,
+            loan_deadline: self.loan_deadline,
+            loaner_id: self.loaner_id,
+            loan_interest,
+            loan_fee,
+            loan_period,
+            loan_block: None,
+            rewards_eligible: true,
+            closed_time: None,
+            version: 0,
+            listing_expires_at: None,
+        }
+    }
 }
 
 #[derive(BorshSerialize, BorshDeserialize, Serialize, Deserialize,Debug)]
@@ -62,12 +132,36 @@ pub struct Metrics {
     pub total_amount_payed: u128,
     // a flag to start/stop the ntv minting
     pub ntv_status:bool,
-    //multuplier for ntv tokens.
-    pub ntv_multiply:u128,
+    //multuplier of the ntv tokens for the lenders.
+    pub ntv_lender_multiply:u128,
+    //multuplier of the ntv tokens for the borrowers.
+    pub ntv_borrower_multiply:u128,
     //how much money has made by auctions
     pub total_amount_lent: u128,
-    //how much ATH has made by auctions
+    //biggest loan funded since the beginning
     pub loan_current_ath: u128,
+    //id of the biggest loan funded
+    pub loan_ath_id: Option<LoanId>,
+    //how many loans there are on each status
+    pub status_counts: StatusCounts,
+    //principal of the loans running
+    pub total_outstanding_principal: u128,
+    //interest payed to the lenders since the beginning
+    pub total_interest_paid: u128,
+    //principal of the loans that expired without being payed
+    pub total_defaulted_amount: u128,
+    //expired loans over the finished loans (expired and payed), 10000=100%
+    pub default_rate: u64,
+    //fees collected by the treasury since the beginning
+    pub total_fees_collected: u128,
+    //fees waiting in the contract to be withdrawn
+    pub treasury_balance: u128,
+    //ntv recorded as rewards since the beginning
+    pub ntv_total_emitted: u128,
+    //ntv minted on the token contract since the beginning
+    pub ntv_total_minted: u128,
+    //ntv that can still be recorded as rewards, None if there is no cap
+    pub ntv_remaining_allowance: Option<u128>,
 }
 
 /// This is format of output via JSON for the Loan.
@@ -78,13 +172,734 @@ pub struct LoanOutput {
     pub id: LoanId,
     #[serde(flatten)]
     pub loan: Loan,
+    /// The loan is pending but it cannot be funded anymore
+    pub listing_expired: bool,
 }
 /// This is format of output via JSON for the loan message.
 #[derive( Serialize, Deserialize)]
 #[serde(crate = "near_sdk::serde")]
 pub struct MsgInput {
     pub description: Option<String>,
-    pub loan_amount_requested: u128,
+    /// Required to list the NFT when there is no offer to accept
+    pub loan_amount_requested: Option<u128>,
+    /// Offer that funds the loan right away with its terms
+    pub accept_offer: Option<OfferId>,
+    /// Time the loan can be funded, the default listing ttl if it is missing
+    pub listing_ttl: Option<u64>,
+}
+
+
+
+/// Operations that can be stopped during an incident.
+#[derive(BorshSerialize, BorshDeserialize, Serialize, Deserialize, Clone, Copy, PartialEq, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub enum Operation {
+    /// New listings received through nft_on_transfer
+    Listing,
+    /// Lenders funding pending loans
+    Funding,
+    /// Minting of NTV rewards
+    NtvMinting,
+    /// Lenders claiming the NFT of an expired loan
+    Liquidation,
+}
+
+/// Pause flags for every operation that can be stopped.
+/// Repayments and the withdraw of the NFT by its owner are never paused.
+#[derive(BorshSerialize, BorshDeserialize, Serialize, Deserialize, Clone, Default, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct PauseState {
+    pub listing: bool,
+    pub funding: bool,
+    pub ntv_minting: bool,
+    pub liquidation: bool,
 }
 
+impl PauseState {
+    pub fn is_paused(&self, operation: Operation) -> bool {
+        match operation {
+            Operation::Listing => self.listing,
+            Operation::Funding => self.funding,
+            Operation::NtvMinting => self.ntv_minting,
+            Operation::Liquidation => self.liquidation,
+        }
+    }
+
+    pub fn set(&mut self, operation: Operation, paused: bool) {
+        match operation {
+            Operation::Listing => self.listing = paused,
+            Operation::Funding => self.funding = paused,
+            Operation::NtvMinting => self.ntv_minting = paused,
+            Operation::Liquidation => self.liquidation = paused,
+        }
+    }
+}
+
+/// Roles that can be granted to accounts for the privileged methods.
+/// The owner of the contract implicitly holds all of them.
+#[derive(BorshSerialize, BorshDeserialize, Serialize, Deserialize, Clone, Copy, PartialEq, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub enum Role {
+    /// Grants and revokes roles, manages NTV settings
+    Admin,
+    /// Manages fees and the treasury
+    FeeManager,
+    /// Manages the settings of the NFT collections
+    CollectionCurator,
+    /// Pauses and resumes the operations of the contract
+    Pauser,
+    /// Deploys new versions of the contract
+    Upgrader,
+}
+
+pub const ALL_ROLES: [Role; 5] = [
+    Role::Admin,
+    Role::FeeManager,
+    Role::CollectionCurator,
+    Role::Pauser,
+    Role::Upgrader,
+];
+
+/// Parameters of the contract returned by get_config.
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct Config {
+    pub owner_account_id: AccountId,
+    pub treasury_account_id: AccountId,
+    //10000=100%
+    pub contract_interest: u64,
+    //10000=100%
+    pub contract_fee: u64,
+    pub payment_period: u64,
+    pub is_minting_ntv: bool,
+    pub ntv_lender_multiply: u128,
+    pub ntv_borrower_multiply: u128,
+    pub ntv_reward_basis: RewardBasis,
+    pub ntv_reward_rules: RewardRules,
+    pub ntv_token_contract: AccountId,
+    pub ntv_vesting_period: u64,
+    pub liquidation_grace_period: u64,
+    pub liquidation_bounty: U128,
+    pub default_listing_ttl: Option<u64>,
+}
+
+/// Fees accrued by the treasury for a currency.
+#[derive(BorshSerialize, BorshDeserialize, Serialize, Deserialize, Clone, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct TreasuryBalance {
+    /// Fees waiting in the contract to be withdrawn
+    pub balance: U128,
+    /// Fees collected since the beginning
+    pub total_collected: U128,
+    /// Fees withdrawn since the beginning
+    pub total_withdrawn: U128,
+}
+
+impl Default for TreasuryBalance {
+    fn default() -> Self {
+        Self {
+            balance: U128(0),
+            total_collected: U128(0),
+            total_withdrawn: U128(0),
+        }
+    }
+}
+
+/// Network where the contract is deployed, it sets the defaults of the contract.
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub enum NetworkProfile {
+    Mainnet,
+    Testnet,
+    /// Local sandbox used for the integration tests
+    Sandbox,
+}
+
+impl Default for NetworkProfile {
+    //the default profile is selected with the cargo features, testnet if none is enabled
+    fn default() -> Self {
+        if cfg!(feature = "mainnet") {
+            NetworkProfile::Mainnet
+        } else if cfg!(feature = "sandbox") {
+            NetworkProfile::Sandbox
+        } else {
+            NetworkProfile::Testnet
+        }
+    }
+}
+
+impl NetworkProfile {
+    pub fn ntv_token_contract(&self) -> AccountId {
+        match self {
+            NetworkProfile::Mainnet => "nativo_token.near",
+            NetworkProfile::Testnet => "nativo_token.testnet",
+            NetworkProfile::Sandbox => "nativo_token.test.near",
+        }
+        .parse()
+        .unwrap()
+    }
+
+    pub fn payment_period(&self) -> u64 {
+        match self {
+            NetworkProfile::Mainnet | NetworkProfile::Testnet => 1_000_000_000 * 60 * 60 * 24 * 7,
+            NetworkProfile::Sandbox => MIN_PAYMENT_PERIOD,
+        }
+    }
+}
+
+/// NTV rewards of an account. Each tranche vests linearly over its own period.
+#[derive(BorshSerialize, BorshDeserialize, Clone, Default, Debug)]
+pub struct RewardAccount {
+    /// Rewards that can be claimed
+    pub unlocked: u128,
+    /// Rewards still vesting
+    pub tranches: Vec<VestingTranche>,
+    /// Rewards minted since the beginning
+    pub claimed: u128,
+}
+
+/// Rewards accrued the same day, they vest linearly between start and end.
+#[derive(BorshSerialize, BorshDeserialize, Clone, Debug)]
+pub struct VestingTranche {
+    pub amount: u128,
+    /// Part of the amount already moved to the unlocked rewards
+    pub vested: u128,
+    pub start: u64,
+    pub end: u64,
+}
+
+/// This is format of output via JSON for the rewards of an account.
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct PendingRewards {
+    /// Rewards that can be claimed now
+    pub claimable: U128,
+    /// Rewards still vesting
+    pub locked: U128,
+    /// When all the rewards will be vested
+    pub vesting_end: u64,
+    /// Rewards claimed since the beginning
+    pub claimed: U128,
+}
+
+/// NTV mint rejected by the token contract, waiting to be retried.
+#[derive(BorshSerialize, BorshDeserialize, Serialize, Deserialize, Clone, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct FailedMint {
+    pub amount: U128,
+    /// When the mint failed
+    pub failed_at: u64,
+}
+
+/// Amount of a loan used to calculate its NTV rewards.
+#[derive(BorshSerialize, BorshDeserialize, Serialize, Deserialize, Clone, Copy, PartialEq, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub enum RewardBasis {
+    /// The amount lent
+    Principal,
+    /// The interest paid by the borrower
+    InterestPaid,
+    /// The amount lent weighted by the time it was outstanding over the loan period
+    TimeWeightedPrincipal,
+}
+
+/// This is format of output via JSON for the boost of a collection.
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct CollectionBoost {
+    pub nft_contract: AccountId,
+    //10000=1x
+    pub boost: u64,
+}
+
+/// NTV liquidity-mining campaign created by an admin.
+#[derive(BorshSerialize, BorshDeserialize, Serialize, Deserialize, Clone, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct Campaign {
+    pub name: String,
+    /// When the campaign starts rewarding the loans
+    pub start_time: u64,
+    /// When the campaign stops rewarding the loans
+    pub end_time: u64,
+    /// Total NTV that can be distributed by the campaign
+    pub budget: U128,
+    /// NTV distributed so far
+    pub distributed: U128,
+    /// Collections rewarded by the campaign, all of them if it is empty
+    pub eligible_collections: Vec<AccountId>,
+    /// Reward for each party of the loan over the reward basis, 10000=1x
+    pub reward_rate: u64,
+}
+
+impl Campaign {
+    pub fn remaining_budget(&self) -> u128 {
+        self.budget.0 - self.distributed.0
+    }
+
+    pub fn is_active(&self, now: u64) -> bool {
+        self.start_time <= now && now < self.end_time && self.remaining_budget() > 0
+    }
+
+    pub fn is_eligible(&self, nft_contract: &AccountId) -> bool {
+        self.eligible_collections.is_empty() || self.eligible_collections.contains(nft_contract)
+    }
+}
+
+/// This is format of output via JSON for the campaigns.
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct CampaignOutput {
+    pub id: CampaignId,
+    #[serde(flatten)]
+    pub campaign: Campaign,
+    pub remaining_budget: U128,
+    /// Part of the budget already distributed, 10000=100%
+    pub progress: u64,
+    pub is_active: bool,
+}
+
+/// Schedule that scales down the NTV rewards over time.
+/// Every epoch the rewards are multiplied by the decay, 5000 halves them.
+#[derive(BorshSerialize, BorshDeserialize, Serialize, Deserialize, Clone, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct EmissionSchedule {
+    /// When the first epoch starts
+    pub start_time: u64,
+    /// Duration of an epoch, 0 to disable the decay
+    pub epoch_length: u64,
+    /// Part of the rewards kept every epoch, 10000=100%
+    pub decay: u64,
+}
+
+impl Default for EmissionSchedule {
+    fn default() -> Self {
+        Self {
+            start_time: 0,
+            epoch_length: 0,
+            decay: BASIS_POINTS,
+        }
+    }
+}
+
+impl EmissionSchedule {
+    pub fn epoch(&self, now: u64) -> u64 {
+        if self.epoch_length == 0 || now <= self.start_time {
+            return 0;
+        }
+        (now - self.start_time) / self.epoch_length
+    }
+
+    //factor applied to the rewards at a given time, 10000=100%
+    pub fn factor(&self, now: u64) -> u64 {
+        let mut factor: u64 = BASIS_POINTS;
+        for _ in 0..self.epoch(now) {
+            factor = factor * self.decay / BASIS_POINTS;
+            if factor == 0 || self.decay == BASIS_POINTS {
+                break;
+            }
+        }
+        factor
+    }
+}
 
+/// This is format of output via JSON for the NTV emission.
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct EmissionState {
+    /// Lifetime cap of the NTV rewards, None if there is no cap
+    pub cap: Option<U128>,
+    pub total_emitted: U128,
+    pub total_minted: U128,
+    pub remaining_allowance: Option<U128>,
+    pub schedule: EmissionSchedule,
+    pub current_epoch: u64,
+    /// Factor applied to the rewards now, 10000=100%
+    pub current_factor: u64,
+}
+
+/// Rules that withhold the NTV rewards of the loans that look like wash trading.
+#[derive(BorshSerialize, BorshDeserialize, Serialize, Deserialize, Clone, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct RewardRules {
+    /// Minimum time between the loan and the payment to get rewards
+    pub min_loan_age: u64,
+    /// Loans payed within this number of blocks since the loan get no rewards
+    pub block_window: u64,
+    /// An NFT relisted within this time since its last payment gets no rewards
+    pub relist_cooldown: u64,
+}
+
+impl Default for RewardRules {
+    fn default() -> Self {
+        Self {
+            min_loan_age: MAX_MIN_LOAN_AGE,
+            block_window: 10,
+            relist_cooldown: 1_000_000_000 * 60 * 60 * 24,
+        }
+    }
+}
+
+/// This is format of output via JSON for the loans pruned.
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct PruneResult {
+    pub pruned: u64,
+    /// Index to send as from_index to scan the next loans of the status
+    pub next_index: U128,
+}
+
+/// Compact record kept for a loan after it is pruned from loans_by_id.
+#[derive(BorshSerialize, BorshDeserialize, Serialize, Deserialize, Clone, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct LoanSummary {
+    pub nft_owner: AccountId,
+    pub nft_contract: AccountId,
+    pub nft_id: String,
+    pub loan_requested: u128,
+    pub loan_payback: u128,
+    pub status: LoanStatus,
+    pub loaner_id: Option<AccountId>,
+    pub submission_time: EpochHeight,
+    pub closed_time: Option<EpochHeight>,
+}
+
+impl From<&Loan> for LoanSummary {
+    fn from(loan: &Loan) -> Self {
+        Self {
+            nft_owner: loan.nft_owner.clone(),
+            nft_contract: loan.nft_contract.clone(),
+            nft_id: loan.nft_id.clone(),
+            loan_requested: loan.loan_requested,
+            loan_payback: loan.loan_payback,
+            status: loan.status.clone(),
+            loaner_id: loan.loaner_id.clone(),
+            submission_time: loan.submission_time,
+            closed_time: loan.closed_time,
+        }
+    }
+}
+
+/// This is format of output via JSON for the history of a loan.
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct LoanHistoryOutput {
+    pub id: LoanId,
+    /// True if the loan was pruned and only its summary is kept
+    pub archived: bool,
+    #[serde(flatten)]
+    pub summary: LoanSummary,
+}
+
+/// Borrowing and lending statistics of an account.
+#[derive(BorshSerialize, BorshDeserialize, Serialize, Deserialize, Clone, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct AccountStats {
+    /// Loans received as borrower
+    pub loans_borrowed: u64,
+    pub total_borrowed: U128,
+    pub loans_repaid: u64,
+    /// Amount payed back including the interest
+    pub total_repaid: U128,
+    pub loans_defaulted: u64,
+    pub total_defaulted: U128,
+    /// Listings canceled by the borrower
+    pub loans_canceled: u64,
+    /// Principal of the loans received that are still running
+    pub outstanding_borrowed: U128,
+    /// Loans given as lender
+    pub loans_lent: u64,
+    pub total_lent: U128,
+    pub interest_earned: U128,
+    /// NFTs claimed from defaulted loans
+    pub collateral_acquired: u64,
+    /// Principal of the loans given that are still running
+    pub outstanding_lent: U128,
+    /// NTV recorded as rewards
+    pub ntv_earned: U128,
+}
+
+impl Default for AccountStats {
+    fn default() -> Self {
+        Self {
+            loans_borrowed: 0,
+            total_borrowed: U128(0),
+            loans_repaid: 0,
+            total_repaid: U128(0),
+            loans_defaulted: 0,
+            total_defaulted: U128(0),
+            loans_canceled: 0,
+            outstanding_borrowed: U128(0),
+            loans_lent: 0,
+            total_lent: U128(0),
+            interest_earned: U128(0),
+            collateral_acquired: 0,
+            outstanding_lent: U128(0),
+            ntv_earned: U128(0),
+        }
+    }
+}
+
+/// How many loans there are on each status.
+#[derive(BorshSerialize, BorshDeserialize, Serialize, Deserialize, Clone, Default, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct StatusCounts {
+    pub pending: u64,
+    pub loaned: u64,
+    pub expired: u64,
+    pub payed: u64,
+    pub canceled: u64,
+    pub listing_expired: u64,
+}
+
+impl StatusCounts {
+    fn count_mut(&mut self, status: &LoanStatus) -> &mut u64 {
+        match status {
+            LoanStatus::Pending => &mut self.pending,
+            LoanStatus::Loaned => &mut self.loaned,
+            LoanStatus::Expired => &mut self.expired,
+            LoanStatus::Payed => &mut self.payed,
+            LoanStatus::Canceled => &mut self.canceled,
+            LoanStatus::ListingExpired => &mut self.listing_expired,
+        }
+    }
+
+    pub fn increment(&mut self, status: &LoanStatus) {
+        *self.count_mut(status) += 1;
+    }
+
+    pub fn decrement(&mut self, status: &LoanStatus) {
+        let count = self.count_mut(status);
+        *count = count.saturating_sub(1);
+    }
+}
+
+/// Metrics of the protocol at the end of a day, used for the charts.
+#[derive(BorshSerialize, BorshDeserialize, Serialize, Deserialize, Clone, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct DailySnapshot {
+    /// Days since the unix epoch
+    pub day: u64,
+    /// Timestamp of the last update of the snapshot
+    pub timestamp: u64,
+    pub total_loans: u64,
+    pub status_counts: StatusCounts,
+    pub total_amount_lent: U128,
+    pub total_amount_payed: U128,
+    pub total_outstanding_principal: U128,
+    pub total_interest_paid: U128,
+    pub total_defaulted_amount: U128,
+    pub total_fees_collected: U128,
+}
+
+/// Field used to sort the pending loans.
+#[derive(BorshSerialize, BorshDeserialize, Serialize, Deserialize, Clone, Copy, PartialEq, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub enum SortBy {
+    /// loan_requested
+    Amount,
+    /// loan_interest
+    Interest,
+    /// loan_period
+    Duration,
+    /// submission_time
+    ListingTime,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub enum SortOrder {
+    Ascending,
+    Descending,
+}
+
+/// Position of the last loan returned, the next page starts after it.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct SortCursor {
+    pub value: U128,
+    pub loan_id: LoanId,
+}
+
+/// This is format of output via JSON for a page of sorted loans.
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct LoansPage {
+    pub loans: Vec<LoanOutput>,
+    /// Cursor to request the next page, None if there are no more loans
+    pub next_cursor: Option<SortCursor>,
+}
+
+impl Loan {
+    //value of the loan for a sort field
+    pub fn sort_value(&self, sort_by: SortBy) -> u128 {
+        match sort_by {
+            SortBy::Amount => self.loan_requested,
+            SortBy::Interest => self.loan_interest as u128,
+            SortBy::Duration => self.loan_period as u128,
+            SortBy::ListingTime => self.submission_time as u128,
+        }
+    }
+
+    //a pending loan that cannot be funded anymore
+    pub fn is_listing_expired(&self, now: EpochHeight) -> bool {
+        self.status == LoanStatus::Pending
+            && self.listing_expires_at.is_some_and(|listing_expires_at| now >= listing_expires_at)
+    }
+
+    //the first term that differs from the terms the lender expects, if any
+    pub fn terms_mismatch(
+        &self,
+        expected_payback: Option<u128>,
+        expected_period: Option<u64>,
+        expected_version: Option<u32>,
+    ) -> Option<&'static str> {
+        if expected_payback.is_some_and(|payback| payback != self.loan_payback) {
+            return Some("loan_payback");
+        }
+        if expected_period.is_some_and(|period| period != self.loan_period) {
+            return Some("loan_period");
+        }
+        if expected_version.is_some_and(|version| version != self.version) {
+            return Some("version");
+        }
+        None
+    }
+}
+
+/// Standing offer of a lender to fund loans of any NFT of a collection.
+#[derive(BorshSerialize, BorshDeserialize, Serialize, Deserialize, Clone, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct Offer {
+    /// Account that funds the loans and gets the escrow back on cancel
+    pub lender_id: AccountId,
+    /// Collection of the NFTs accepted as collateral
+    pub nft_contract: AccountId,
+    /// Principal of each loan
+    pub amount: U128,
+    /// Interest of each loan, 10000=100%
+    pub interest: u64,
+    /// Duration of the payment period of each loan
+    pub period: u64,
+    /// Loans that can still be funded by the offer
+    pub remaining: u32,
+    pub created_at: u64,
+}
+
+impl Offer {
+    //deposit still held by the contract for the loans remaining
+    pub fn escrow(&self) -> u128 {
+        self.amount.0 * self.remaining as u128
+    }
+}
+
+/// This is format of output via JSON for the offers.
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct OfferOutput {
+    pub id: OfferId,
+    #[serde(flatten)]
+    pub offer: Offer,
+    pub escrow: U128,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn loan() -> Loan {
+        Loan {
+            nft_owner: "owner.near".parse().unwrap(),
+            nft_contract: "nft.near".parse().unwrap(),
+            nft_id: "1".to_string(),
+            description: None,
+            loan_requested: 1000,
+            loan_payback: 1100,
+            status: LoanStatus::Pending,
+            submission_time: 0,
+            loan_time: None,
+            loan_deadline: None,
+            loaner_id: None,
+            loan_interest: 1000,
+            loan_fee: 200,
+            loan_period: 100,
+            loan_block: None,
+            rewards_eligible: true,
+            closed_time: None,
+            version: 2,
+            listing_expires_at: None,
+        }
+    }
+
+    fn schedule(epoch_length: u64, decay: u64) -> EmissionSchedule {
+        EmissionSchedule {
+            start_time: 1000,
+            epoch_length,
+            decay,
+        }
+    }
+
+    #[test]
+    fn factor_is_full_before_the_first_epoch_or_without_decay() {
+        assert_eq!(EmissionSchedule::default().factor(u64::MAX), BASIS_POINTS);
+        assert_eq!(schedule(0, 5000).factor(u64::MAX), BASIS_POINTS);
+        assert_eq!(schedule(100, 5000).factor(0), BASIS_POINTS);
+        assert_eq!(schedule(100, 5000).factor(1099), BASIS_POINTS);
+        assert_eq!(schedule(1, BASIS_POINTS).factor(u64::MAX), BASIS_POINTS);
+    }
+
+    #[test]
+    fn factor_decays_every_epoch() {
+        let schedule = schedule(100, 5000);
+        assert_eq!(schedule.epoch(1100), 1);
+        assert_eq!(schedule.factor(1100), 5000);
+        assert_eq!(schedule.factor(1299), 2500);
+        assert_eq!(schedule.factor(1300), 1250);
+    }
+
+    #[test]
+    fn factor_reaches_zero_and_stops() {
+        assert_eq!(schedule(100, 0).factor(1100), 0);
+        //the loop ends once the factor is 0 even with a huge number of epochs
+        assert_eq!(schedule(1, 9999).factor(u64::MAX), 0);
+    }
+
+    #[test]
+    fn terms_match_when_nothing_is_expected() {
+        assert_eq!(loan().terms_mismatch(None, None, None), None);
+        assert_eq!(loan().terms_mismatch(Some(1100), Some(100), Some(2)), None);
+    }
+
+    #[test]
+    fn terms_mismatch_returns_the_first_term_that_changed() {
+        let loan = loan();
+        assert_eq!(loan.terms_mismatch(Some(1000), None, None), Some("loan_payback"));
+        assert_eq!(loan.terms_mismatch(None, Some(200), None), Some("loan_period"));
+        assert_eq!(loan.terms_mismatch(None, None, Some(1)), Some("version"));
+        assert_eq!(loan.terms_mismatch(Some(1000), Some(200), Some(1)), Some("loan_payback"));
+        assert_eq!(loan.terms_mismatch(Some(1100), Some(200), Some(1)), Some("loan_period"));
+    }
+
+    #[test]
+    fn a_listing_without_ttl_never_expires() {
+        assert!(!loan().is_listing_expired(u64::MAX));
+    }
+
+    #[test]
+    fn a_pending_listing_expires_at_its_expiry() {
+        let mut loan = loan();
+        loan.listing_expires_at = Some(500);
+        assert!(!loan.is_listing_expired(499));
+        assert!(loan.is_listing_expired(500));
+        assert!(loan.is_listing_expired(501));
+    }
+
+    #[test]
+    fn only_a_pending_listing_expires() {
+        let mut loan = loan();
+        loan.listing_expires_at = Some(500);
+        for status in [LoanStatus::Loaned, LoanStatus::Canceled, LoanStatus::ListingExpired] {
+            loan.status = status;
+            assert!(!loan.is_listing_expired(501));
+        }
+    }
+}
@@ -0,0 +1,69 @@
+use crate::*;
+
+/// Days kept in the ring buffer of daily snapshots
+pub const MAX_DAILY_SNAPSHOTS: u64 = 365;
+/// Nanoseconds in a day
+pub const NS_PER_DAY: u64 = 1_000_000_000 * 60 * 60 * 24;
+
+#[near_bindgen]
+impl NFTLoans {
+    //daily metrics between two timestamps, only the last MAX_DAILY_SNAPSHOTS days are kept
+    pub fn get_daily_snapshots(&self, from_timestamp: u64, to_timestamp: u64) -> Vec<DailySnapshot> {
+        let from_day = from_timestamp / NS_PER_DAY;
+        let to_day = to_timestamp / NS_PER_DAY;
+        let today = env::block_timestamp() / NS_PER_DAY;
+        //the days older than the ring buffer were overwritten
+        let from_day = max(from_day, (today + 1).saturating_sub(MAX_DAILY_SNAPSHOTS));
+        let to_day = min(to_day, today);
+        if from_day > to_day {
+            return vec![];
+        }
+
+        (from_day..=to_day)
+            .filter_map(|day| {
+                self.daily_snapshots
+                    .get(&(day % MAX_DAILY_SNAPSHOTS))
+                    .filter(|snapshot| snapshot.day == day)
+            })
+            .collect()
+    }
+}
+
+impl NFTLoans {
+    //update the protocol totals when a loan changes of status
+    pub(crate) fn internal_record_protocol_metrics(&mut self, loan: &Loan, previous_status: &LoanStatus) {
+        let principal = loan.loan_requested;
+        self.status_counts.decrement(previous_status);
+        self.status_counts.increment(&loan.status);
+
+        if *previous_status == LoanStatus::Loaned {
+            self.total_outstanding_principal = self.total_outstanding_principal.saturating_sub(principal);
+        }
+        match loan.status {
+            LoanStatus::Loaned => self.total_outstanding_principal += principal,
+            LoanStatus::Payed => self.total_interest_paid += loan.loan_payback - principal,
+            LoanStatus::Expired => self.total_defaulted_amount += principal,
+            LoanStatus::Pending | LoanStatus::Canceled | LoanStatus::ListingExpired => {}
+        }
+        self.internal_record_snapshot();
+    }
+
+    //overwrite the snapshot of today with the current metrics
+    pub(crate) fn internal_record_snapshot(&mut self) {
+        let now = env::block_timestamp();
+        let day = now / NS_PER_DAY;
+        let snapshot = DailySnapshot {
+            day,
+            timestamp: now,
+            total_loans: self.last_loan_id,
+            status_counts: self.status_counts.clone(),
+            total_amount_lent: U128(self.total_amount_lent),
+            total_amount_payed: U128(self.total_amount_payed),
+            total_outstanding_principal: U128(self.total_outstanding_principal),
+            total_interest_paid: U128(self.total_interest_paid),
+            total_defaulted_amount: U128(self.total_defaulted_amount),
+            total_fees_collected: self.internal_get_treasury(NEAR_CURRENCY).total_collected,
+        };
+        self.daily_snapshots.insert(&(day % MAX_DAILY_SNAPSHOTS), &snapshot);
+    }
+}
@@ -12,7 +12,7 @@ impl NFTLoans {
     #[cfg(target_arch = "wasm32")]
     pub fn upgrade(self) {
         use near_sys as sys;
-        // assert!(env::predecessor_account_id() == self.minter_account_id);
+        self.assert_role(Role::Upgrader);
         //input is code:<Vec<u8> on REGISTER 0
         //log!("bytes.length {}", code.unwrap().len());
         const GAS_FOR_UPGRADE: u64 = 20 * TGAS; //gas occupied by this fn
@@ -52,6 +52,8 @@ impl NFTLoans {
     #[init(ignore_state)]
     pub fn migrate() -> Self {
         let old_state: OldNFTLoans = env::state_read().expect("failed");
+        //the loans keep the old layout until migrate_loans converts them
+        let loans_to_migrate = old_state.loans_by_id.len();
         
         env::log_str("old state readed");
         Self {
@@ -69,8 +71,269 @@ impl NFTLoans {
             payment_period: old_state.payment_period,
             contract_fee:old_state.contract_fee,
             is_minting_ntv: old_state.is_minting_ntv,
-            ntv_multiply: old_state.ntv_multiply,
+            ntv_lender_multiply: old_state.ntv_multiply,
+            ntv_borrower_multiply: old_state.ntv_multiply,
+            role_members: LookupMap::new(StorageKey::RoleMembers.try_to_vec().unwrap()),
+            pause_state: PauseState::default(),
+            proposed_owner_account_id: None,
+            treasury: UnorderedMap::new(StorageKey::Treasury.try_to_vec().unwrap()),
+            ntv_token_contract: NetworkProfile::default().ntv_token_contract(),
+            ntv_rewards: LookupMap::new(StorageKey::NtvRewards.try_to_vec().unwrap()),
+            ntv_vesting_period: 0,
+            failed_mints: LookupMap::new(StorageKey::FailedMints.try_to_vec().unwrap()),
+            collection_boosts: UnorderedMap::new(StorageKey::CollectionBoosts.try_to_vec().unwrap()),
+            ntv_reward_basis: RewardBasis::InterestPaid,
+            campaigns: UnorderedMap::new(StorageKey::Campaigns.try_to_vec().unwrap()),
+            active_campaigns: UnorderedSet::new(StorageKey::ActiveCampaigns.try_to_vec().unwrap()),
+            last_campaign_id: 0,
+            ntv_emission_cap: None,
+            ntv_total_emitted: 0,
+            ntv_total_minted: 0,
+            ntv_emission_schedule: EmissionSchedule::default(),
+            ntv_reward_rules: RewardRules::default(),
+            last_payment_by_nft: LookupMap::new(StorageKey::LastPaymentByNft.try_to_vec().unwrap()),
+            loans_by_status: LookupMap::new(StorageKey::LoansByStatus.try_to_vec().unwrap()),
+            loans_by_collection_status: LookupMap::new(StorageKey::LoansByCollectionStatus.try_to_vec().unwrap()),
+            loan_by_nft: LookupMap::new(StorageKey::LoanByNft.try_to_vec().unwrap()),
+            archived_loans: LookupMap::new(StorageKey::ArchivedLoans.try_to_vec().unwrap()),
+            loan_retention_period: DEFAULT_LOAN_RETENTION_PERIOD,
+            account_stats: LookupMap::new(StorageKey::AccountStats.try_to_vec().unwrap()),
+            loan_ath_id: None,
+            status_counts: StatusCounts::default(),
+            total_outstanding_principal: 0,
+            total_interest_paid: 0,
+            total_defaulted_amount: 0,
+            daily_snapshots: LookupMap::new(StorageKey::DailySnapshots.try_to_vec().unwrap()),
+            pending_by_amount: TreeMap::new(StorageKey::PendingByAmount.try_to_vec().unwrap()),
+            pending_by_interest: TreeMap::new(StorageKey::PendingByInterest.try_to_vec().unwrap()),
+            pending_by_duration: TreeMap::new(StorageKey::PendingByDuration.try_to_vec().unwrap()),
+            pending_by_listing_time: TreeMap::new(StorageKey::PendingByListingTime.try_to_vec().unwrap()),
+            loaned_by_deadline: TreeMap::new(StorageKey::LoanedByDeadline.try_to_vec().unwrap()),
+            liquidation_grace_period: DEFAULT_LIQUIDATION_GRACE_PERIOD,
+            liquidation_bounty: DEFAULT_LIQUIDATION_BOUNTY,
+            default_listing_ttl: None,
+            offers: UnorderedMap::new(StorageKey::Offers.try_to_vec().unwrap()),
+            offers_by_collection: LookupMap::new(StorageKey::OffersByCollection.try_to_vec().unwrap()),
+            last_offer_id: 0,
+            loans_to_migrate,
+            next_loan_to_migrate: 0,
         }
     }
 
-}
\ No newline at end of file
+    //convert the loans stored before the upgrade to the current layout, they are read by position
+    //it has to be called after the upgrade until it returns 0, the loans not converted cannot be read
+    pub fn migrate_loans(&mut self, limit: Option<u64>) -> u64 {
+        self.assert_role(Role::Upgrader);
+        let start = self.next_loan_to_migrate;
+        let end = min(self.loans_to_migrate, start + limit.unwrap_or(50));
+
+        for index in start..end {
+            let key_raw = self.loans_by_id.keys_as_vector().get_raw(index).unwrap();
+            let value_raw = self.loans_by_id.values_as_vector().get_raw(index).unwrap();
+            let loan = OldLoan::try_from_slice(&value_raw).unwrap().into_loan(
+                self.contract_interest,
+                self.contract_fee,
+                self.payment_period,
+            );
+            //insert would read the replaced value with the new layout, so the raw bytes are written
+            self.loans_by_id.insert_raw(&key_raw, &loan.try_to_vec().unwrap());
+        }
+        self.next_loan_to_migrate = end;
+
+        log_event(
+            "loans_migrated",
+            json!({ "from_index": start, "to_index": end }),
+        );
+        self.loans_to_migrate - end
+    }
+
+    //add the loans stored before the indexes existed to them, the loans already indexed are skipped
+    //so it can be called again for the same loans, it returns how many loans were added
+    pub fn reindex_loans(&mut self, from_index: Option<U128>, limit: Option<u64>) -> u64 {
+        self.assert_role(Role::Admin);
+        self.assert_loans_migrated();
+        let start = u128::from(from_index.unwrap_or(U128(0)));
+        let loans: Vec<(LoanId, Loan)> = self
+            .loans_by_id
+            .iter()
+            .skip(start as usize)
+            .take(limit.unwrap_or(50) as usize)
+            .collect();
+
+        let mut reindexed: u64 = 0;
+        for (loan_id, loan) in loans {
+            let indexed = self
+                .loans_by_status
+                .get(&loan.status)
+                .is_some_and(|loans_set| loans_set.contains(&loan_id));
+            if indexed {
+                continue;
+            }
+            self.internal_add_loan_to_status(&loan_id, &loan);
+            self.status_counts.increment(&loan.status);
+            match loan.status {
+                LoanStatus::Pending => {
+                    self.internal_add_loan_to_pending_indexes(&loan_id, &loan);
+                    self.loan_by_nft.insert(&nft_key(&loan.nft_contract, &loan.nft_id), &loan_id);
+                }
+                LoanStatus::Loaned => {
+                    if let Some(loan_deadline) = loan.loan_deadline {
+                        self.loaned_by_deadline.insert(&(loan_deadline, loan_id), &loan_id);
+                    }
+                    self.loan_by_nft.insert(&nft_key(&loan.nft_contract, &loan.nft_id), &loan_id);
+                    self.total_outstanding_principal += loan.loan_requested;
+                    self.loans_active += 1;
+                }
+                _ => {}
+            }
+            reindexed += 1;
+        }
+
+        if reindexed > 0 {
+            self.internal_record_snapshot();
+            log_event("loans_reindexed", json!({ "count": reindexed }));
+        }
+        reindexed
+    }
+}
+
+impl NFTLoans {
+    //the loans removed while some loans have the old layout would change their positions
+    pub(crate) fn assert_loans_migrated(&self) {
+        assert!(
+            self.next_loan_to_migrate >= self.loans_to_migrate,
+            "The loans have not been migrated yet"
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use near_sdk::test_utils::{accounts, VMContextBuilder};
+    use near_sdk::testing_env;
+
+    const NEAR: u128 = 1_000_000_000_000_000_000_000_000;
+
+    fn context(predecessor: AccountId, block_timestamp: u64) -> VMContextBuilder {
+        let mut context = VMContextBuilder::new();
+        context
+            .predecessor_account_id(predecessor.clone())
+            .signer_account_id(predecessor)
+            .block_timestamp(block_timestamp);
+        context
+    }
+
+    fn old_loan(nft_id: &str, status: LoanStatus, loaner_id: Option<AccountId>) -> OldLoan {
+        let loaned = status == LoanStatus::Loaned;
+        OldLoan {
+            nft_owner: accounts(1),
+            nft_contract: accounts(3),
+            nft_id: nft_id.to_string(),
+            description: None,
+            loan_requested: NEAR,
+            loan_payback: NEAR + NEAR / 10,
+            status,
+            submission_time: 0,
+            loan_time: if loaned { Some(10) } else { None },
+            loan_deadline: if loaned { Some(10 + MIN_PAYMENT_PERIOD) } else { None },
+            loaner_id,
+        }
+    }
+
+    //store the state of the contract before the upgrade with the given loans and run migrate
+    fn upgrade_with_loans(loans: Vec<OldLoan>) -> NFTLoans {
+        testing_env!(context(accounts(0), 100).build());
+        let mut old_loans: UnorderedMap<LoanId, OldLoan> =
+            UnorderedMap::new(StorageKey::LoansById.try_to_vec().unwrap());
+        for (loan_id, loan) in loans.iter().enumerate() {
+            old_loans.insert(&(loan_id as LoanId), loan);
+        }
+        let old_state = OldNFTLoans {
+            owner_account_id: accounts(0),
+            treasury_account_id: accounts(0),
+            last_loan_id: loans.len() as u64,
+            contract_interest: 1000,
+            //the old state keeps the prefix and the length of the map, not its values
+            loans_by_id: UnorderedMap::try_from_slice(&old_loans.try_to_vec().unwrap()).unwrap(),
+            loans_per_owner: LookupMap::new(StorageKey::LoansPerOwner.try_to_vec().unwrap()),
+            loans_per_lender: LookupMap::new(StorageKey::LoansPerLender.try_to_vec().unwrap()),
+            total_amount_payed: 0,
+            total_amount_lent: 0,
+            payment_period: MIN_PAYMENT_PERIOD,
+            contract_fee: 200,
+            is_minting_ntv: true,
+            ntv_multiply: 3,
+            loan_current_ath: 0,
+            loans_active: 1,
+        };
+        env::state_write(&old_state);
+        NFTLoans::migrate()
+    }
+
+    #[test]
+    fn migrate_loans_converts_the_old_loans() {
+        let mut contract = upgrade_with_loans(vec![
+            old_loan("1", LoanStatus::Pending, None),
+            old_loan("2", LoanStatus::Loaned, Some(accounts(2))),
+            old_loan("3", LoanStatus::Payed, Some(accounts(2))),
+        ]);
+        assert_eq!(contract.loans_to_migrate, 3);
+
+        assert_eq!(contract.migrate_loans(Some(2)), 1);
+        assert_eq!(contract.migrate_loans(Some(2)), 0);
+        contract.assert_loans_migrated();
+
+        let loans: Vec<(LoanId, Loan)> = contract.loans_by_id.iter().collect();
+        assert_eq!(loans.len(), 3);
+        for (loan_id, loan) in loans {
+            assert_eq!(loan.nft_id, (loan_id + 1).to_string());
+            assert_eq!(loan.loan_requested, NEAR);
+            assert_eq!(loan.loan_payback, NEAR + NEAR / 10);
+            assert_eq!(loan.loan_interest, 1000);
+            assert_eq!(loan.loan_fee, 200);
+            assert_eq!(loan.loan_period, MIN_PAYMENT_PERIOD);
+            assert_eq!(loan.version, 0);
+        }
+        let loaned = contract.loans_by_id.get(&1).unwrap();
+        assert_eq!(loaned.status, LoanStatus::Loaned);
+        assert_eq!(loaned.loaner_id, Some(accounts(2)));
+        assert_eq!(loaned.loan_deadline, Some(10 + MIN_PAYMENT_PERIOD));
+    }
+
+    #[test]
+    fn a_loan_funded_before_the_upgrade_can_be_payed_after_the_reindex() {
+        let mut contract = upgrade_with_loans(vec![
+            old_loan("1", LoanStatus::Pending, None),
+            old_loan("2", LoanStatus::Loaned, Some(accounts(2))),
+            old_loan("3", LoanStatus::Payed, Some(accounts(2))),
+        ]);
+        contract.migrate_loans(None);
+        assert_eq!(contract.reindex_loans(None, None), 3);
+        //the loans already indexed are skipped
+        assert_eq!(contract.reindex_loans(None, None), 0);
+        assert_eq!(contract.loans_active, 1);
+        assert_eq!(contract.total_outstanding_principal, NEAR);
+        assert_eq!(contract.status_counts.pending, 1);
+        assert_eq!(contract.status_counts.loaned, 1);
+        assert_eq!(contract.status_counts.payed, 1);
+
+        let mut context = context(accounts(1), 10 + MIN_PAYMENT_PERIOD);
+        testing_env!(context.attached_deposit(NEAR + NEAR / 10).build());
+        let loan = contract.pay_loan(1).unwrap();
+        assert_eq!(loan.status, LoanStatus::Payed);
+        assert_eq!(contract.loans_active, 0);
+        assert_eq!(contract.total_outstanding_principal, 0);
+        assert_eq!(contract.total_interest_paid, NEAR / 10);
+        assert_eq!(contract.status_counts.loaned, 0);
+        assert_eq!(contract.status_counts.payed, 2);
+        assert!(contract.loan_by_nft.get(&nft_key(&accounts(3), "2")).is_none());
+    }
+
+    #[test]
+    #[should_panic(expected = "The loans have not been migrated yet")]
+    fn prune_loans_waits_for_the_migration() {
+        let mut contract = upgrade_with_loans(vec![old_loan("1", LoanStatus::Payed, Some(accounts(2)))]);
+        contract.prune_loans(LoanStatus::Payed, None, None);
+    }
+}
@@ -0,0 +1,200 @@
+use crate::*;
+
+#[near_bindgen]
+impl NFTLoans {
+    //post a standing offer for any NFT of a collection, the deposit is the escrow of every loan (amount * count)
+    #[payable]
+    pub fn create_offer(
+        &mut self,
+        nft_contract: AccountId,
+        amount: U128,
+        interest: u64,
+        period: u64,
+        count: u32,
+    ) -> OfferId {
+        self.assert_not_paused(Operation::Funding);
+        let amount = u128::from(amount);
+        assert!(amount > 0, "The amount should be greater than 0");
+        assert!(count > 0, "The count should be greater than 0");
+        assert_valid_interest(interest);
+        assert_valid_payment_period(period);
+        let escrow = amount
+            .checked_mul(count as u128)
+            .expect("The escrow of the offer is too big");
+        assert_eq!(env::attached_deposit(), escrow, "The deposit should be the amount times the count");
+
+        let id: OfferId = self.last_offer_id;
+        let offer = Offer {
+            lender_id: env::predecessor_account_id(),
+            nft_contract,
+            amount: U128(amount),
+            interest,
+            period,
+            remaining: count,
+            created_at: env::block_timestamp(),
+        };
+        self.offers.insert(&id, &offer);
+        internal_add_to_index(
+            &mut self.offers_by_collection,
+            &offer.nft_contract,
+            StorageKey::OffersByCollectionInner {
+                account_id_hash: hash_account_id(&offer.nft_contract),
+            },
+            &id,
+        );
+        self.last_offer_id += 1;
+
+        log_event("offer_created", json!({ "offer_id": id, "offer": offer }));
+        id
+    }
+
+    //remove an offer and return the escrow of the loans remaining to the lender
+    pub fn cancel_offer(&mut self, offer_id: OfferId) -> U128 {
+        let offer = self.offers.get(&offer_id).expect("The offer does not exist");
+        assert_eq!(
+            env::predecessor_account_id(),
+            offer.lender_id,
+            "You are not the lender of this offer"
+        );
+
+        let escrow = offer.escrow();
+        self.internal_remove_offer(&offer_id, &offer);
+        if escrow > 0 {
+            Promise::new(offer.lender_id.clone()).transfer(escrow);
+        }
+        log_event(
+            "offer_canceled",
+            json!({ "offer_id": offer_id, "refund": U128(escrow) }),
+        );
+        U128(escrow)
+    }
+
+    pub fn get_offer(&self, offer_id: OfferId) -> Option<OfferOutput> {
+        self.offers
+            .get(&offer_id)
+            .map(|offer| offer_output(offer_id, offer))
+    }
+
+    //Query for the offers using pagination
+    pub fn get_offers(&self, from_index: Option<U128>, limit: Option<u64>) -> Vec<OfferOutput> {
+        let start = u128::from(from_index.unwrap_or(U128(0)));
+
+        self.offers
+            .iter()
+            .skip(start as usize)
+            .take(limit.unwrap_or(50) as usize)
+            .map(|(offer_id, offer)| offer_output(offer_id, offer))
+            .collect()
+    }
+
+    //Query for the offers of a collection using pagination
+    pub fn get_offers_by_collection(
+        &self,
+        nft_contract: AccountId,
+        from_index: Option<U128>,
+        limit: Option<u64>,
+    ) -> Vec<OfferOutput> {
+        if let Some(offers_set) = self.offers_by_collection.get(&nft_contract) {
+            let start = u128::from(from_index.unwrap_or(U128(0)));
+            offers_set
+                .iter()
+                .skip(start as usize)
+                .take(limit.unwrap_or(50) as usize)
+                .filter_map(|offer_id| self.get_offer(offer_id))
+                .collect()
+        } else {
+            vec![]
+        }
+    }
+}
+
+fn offer_output(id: OfferId, offer: Offer) -> OfferOutput {
+    OfferOutput {
+        id,
+        escrow: U128(offer.escrow()),
+        offer,
+    }
+}
+
+impl NFTLoans {
+    //use one of the loans of an offer for an NFT received from the collection
+    //the offer is removed when no loans remain
+    pub(crate) fn internal_take_offer(
+        &mut self,
+        offer_id: OfferId,
+        nft_contract: &AccountId,
+        borrower_id: &AccountId,
+    ) -> Offer {
+        self.assert_not_paused(Operation::Funding);
+        let mut offer = self.offers.get(&offer_id).expect("The offer does not exist");
+        assert_eq!(
+            offer.nft_contract, *nft_contract,
+            "The offer is not for this collection"
+        );
+        assert_ne!(offer.lender_id, *borrower_id, "The owner cannot be the loaner");
+
+        offer.remaining -= 1;
+        if offer.remaining == 0 {
+            self.internal_remove_offer(&offer_id, &offer);
+        } else {
+            self.offers.insert(&offer_id, &offer);
+        }
+        log_event(
+            "offer_accepted",
+            json!({
+                "offer_id": offer_id,
+                "borrower_id": borrower_id,
+                "remaining": offer.remaining,
+            }),
+        );
+        offer
+    }
+
+    fn internal_remove_offer(&mut self, offer_id: &OfferId, offer: &Offer) {
+        self.offers.remove(offer_id);
+        internal_remove_from_index(&mut self.offers_by_collection, &offer.nft_contract, offer_id);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use near_sdk::test_utils::{accounts, VMContextBuilder};
+    use near_sdk::testing_env;
+
+    const NEAR: u128 = 1_000_000_000_000_000_000_000_000;
+
+    fn context(predecessor: AccountId, signer: AccountId, attached_deposit: Balance) -> VMContextBuilder {
+        let mut context = VMContextBuilder::new();
+        context
+            .predecessor_account_id(predecessor)
+            .signer_account_id(signer)
+            .attached_deposit(attached_deposit);
+        context
+    }
+
+    #[test]
+    fn an_offer_accepted_by_an_approved_account_funds_the_owner_of_the_nft() {
+        testing_env!(context(accounts(0), accounts(0), 0).build());
+        let mut contract = NFTLoans::new(accounts(0), accounts(0), 800, 200, None);
+        testing_env!(context(accounts(2), accounts(2), 2 * NEAR).build());
+        let offer_id = contract.create_offer(accounts(3), U128(NEAR), 500, MIN_PAYMENT_PERIOD, 2);
+
+        //the NFT contract calls back with the owner of the token, the signer is an approved account
+        testing_env!(context(accounts(3), accounts(4), 0).build());
+        contract.nft_on_transfer(
+            accounts(4),
+            accounts(1),
+            "1".to_string(),
+            json!({ "accept_offer": offer_id }).to_string(),
+        );
+
+        let loan = contract.loans_by_id.get(&0).unwrap();
+        assert_eq!(loan.nft_owner, accounts(1));
+        assert_eq!(loan.loaner_id, Some(accounts(2)));
+        assert_eq!(loan.status, LoanStatus::Loaned);
+        assert!(contract.loans_per_owner.get(&accounts(1)).unwrap().contains(&0));
+        assert!(contract.loans_per_owner.get(&accounts(4)).is_none());
+        assert_eq!(contract.get_offer(offer_id).unwrap().offer.remaining, 1);
+    }
+}
@@ -0,0 +1,71 @@
+use crate::*;
+
+// The ownership is transfered in two steps so a typo cannot lock the contract,
+// the proposed account (e.g. a DAO contract) has to accept it
+#[near_bindgen]
+impl NFTLoans {
+    pub fn propose_owner(&mut self, new_owner_account_id: AccountId) {
+        self.assert_owner();
+        assert_ne!(
+            new_owner_account_id, self.owner_account_id,
+            "The account is already the owner"
+        );
+        self.proposed_owner_account_id = Some(new_owner_account_id.clone());
+        log_event(
+            "owner_proposed",
+            json!({
+                "owner_account_id": self.owner_account_id,
+                "proposed_owner_account_id": new_owner_account_id,
+            }),
+        );
+    }
+
+    pub fn cancel_ownership_proposal(&mut self) {
+        self.assert_owner();
+        let proposed_owner_account_id = self
+            .proposed_owner_account_id
+            .take()
+            .expect("There is no ownership proposal");
+        log_event(
+            "owner_proposal_canceled",
+            json!({ "proposed_owner_account_id": proposed_owner_account_id }),
+        );
+    }
+
+    pub fn accept_ownership(&mut self) {
+        let account_id = env::predecessor_account_id();
+        assert_eq!(
+            Some(account_id.clone()),
+            self.proposed_owner_account_id,
+            "you aren't the proposed owner"
+        );
+        let old_owner_account_id =
+            std::mem::replace(&mut self.owner_account_id, account_id.clone());
+        self.proposed_owner_account_id = None;
+        log_event(
+            "owner_changed",
+            json!({
+                "old_owner_account_id": old_owner_account_id,
+                "owner_account_id": account_id,
+            }),
+        );
+    }
+
+    pub fn get_owner(&self) -> AccountId {
+        self.owner_account_id.clone()
+    }
+
+    pub fn get_proposed_owner(&self) -> Option<AccountId> {
+        self.proposed_owner_account_id.clone()
+    }
+}
+
+impl NFTLoans {
+    pub(crate) fn assert_owner(&self) {
+        assert_eq!(
+            self.owner_account_id,
+            env::predecessor_account_id(),
+            "you aren't the owner"
+        );
+    }
+}
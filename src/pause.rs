@@ -0,0 +1,55 @@
+use crate::*;
+
+#[near_bindgen]
+impl NFTLoans {
+    //stop or resume an operation of the contract
+    pub fn set_paused(&mut self, operation: Operation, paused: bool) -> PauseState {
+        self.assert_role(Role::Pauser);
+        self.pause_state.set(operation, paused);
+        log_event(
+            if paused { "pause" } else { "unpause" },
+            json!({
+                "operation": operation,
+                "account_id": env::predecessor_account_id(),
+            }),
+        );
+        self.pause_state.clone()
+    }
+
+    //stop every operation that can be paused at once
+    pub fn pause_all(&mut self) -> PauseState {
+        self.assert_role(Role::Pauser);
+        for operation in [
+            Operation::Listing,
+            Operation::Funding,
+            Operation::NtvMinting,
+            Operation::Liquidation,
+        ] {
+            if !self.pause_state.is_paused(operation) {
+                self.pause_state.set(operation, true);
+                log_event(
+                    "pause",
+                    json!({
+                        "operation": operation,
+                        "account_id": env::predecessor_account_id(),
+                    }),
+                );
+            }
+        }
+        self.pause_state.clone()
+    }
+
+    pub fn get_pause_state(&self) -> PauseState {
+        self.pause_state.clone()
+    }
+}
+
+impl NFTLoans {
+    pub(crate) fn assert_not_paused(&self, operation: Operation) {
+        assert!(
+            !self.pause_state.is_paused(operation),
+            "The operation {:?} is paused",
+            operation
+        );
+    }
+}
@@ -0,0 +1,495 @@
+use crate::*;
+
+/// Gas for the mint on the NTV token contract
+pub const GAS_FOR_NTV_MINT: Gas = Gas(10_000_000_000_000);
+/// Gas for the callback of the NTV mint
+pub const GAS_FOR_RESOLVE_MINT: Gas = Gas(10_000_000_000_000);
+/// Boost of a collection without a boost, 10000=1x
+pub const BOOST_BASE: u64 = BASIS_POINTS;
+/// Maximum boost of a collection, 50000=5x
+pub const MAX_COLLECTION_BOOST: u64 = 50_000;
+/// Longest minimum loan age of the reward rules, half of the shortest payment period (12 hours)
+/// so the loans can always be payed with rewards before their deadline
+pub const MAX_MIN_LOAN_AGE: u64 = MIN_PAYMENT_PERIOD / 2;
+/// Longest block window of the reward rules
+pub const MAX_BLOCK_WINDOW: u64 = 10_000;
+
+impl RewardAccount {
+    //move the vested part of every tranche to the unlocked rewards, the tranches fully vested are removed
+    pub(crate) fn settle(&mut self, now: u64) {
+        for tranche in self.tranches.iter_mut() {
+            let vested = if now >= tranche.end {
+                tranche.amount
+            } else if now <= tranche.start {
+                0
+            } else {
+                pro_rata(tranche.amount, now - tranche.start, tranche.end - tranche.start)
+            };
+            self.unlocked += vested - tranche.vested;
+            tranche.vested = vested;
+        }
+        self.tranches.retain(|tranche| tranche.vested < tranche.amount);
+    }
+
+    //add rewards vesting from now, the rewards of the same day share a tranche
+    //so an account keeps at most one tranche for each day of the vesting period
+    pub(crate) fn lock(&mut self, amount: u128, now: u64, vesting_period: u64) {
+        if let Some(tranche) = self.tranches.last_mut() {
+            if tranche.start / NS_PER_DAY == now / NS_PER_DAY {
+                //the vested part is kept and the rest vests until the end of the tranche
+                tranche.amount += amount;
+                return;
+            }
+        }
+        self.tranches.push(VestingTranche {
+            amount,
+            vested: 0,
+            start: now,
+            end: now + vesting_period,
+        });
+    }
+
+    pub fn locked(&self) -> u128 {
+        self.tranches
+            .iter()
+            .map(|tranche| tranche.amount - tranche.vested)
+            .sum()
+    }
+
+    //when all the rewards will be vested
+    pub fn vesting_end(&self) -> u64 {
+        self.tranches.iter().map(|tranche| tranche.end).max().unwrap_or(0)
+    }
+}
+
+#[near_bindgen]
+impl NFTLoans {
+    //mint all the vested NTV rewards of the caller in one call
+    pub fn claim_ntv_rewards(&mut self) -> U128 {
+        self.assert_not_paused(Operation::NtvMinting);
+        let account_id = env::predecessor_account_id();
+        let mut rewards = self
+            .ntv_rewards
+            .get(&account_id)
+            .expect("There are no rewards for this account");
+        rewards.settle(env::block_timestamp());
+        let amount = rewards.unlocked;
+        assert!(amount > 0, "There are no rewards to claim");
+        rewards.unlocked = 0;
+        self.ntv_rewards.insert(&account_id, &rewards);
+
+        self.internal_mint_ntv(&account_id, amount);
+        log_event(
+            "ntv_rewards_claimed",
+            json!({ "account_id": account_id, "amount": U128(amount) }),
+        );
+        U128(amount)
+    }
+
+    //the rewards count as claimed once they are minted
+    //if the NTV contract rejected the mint it is saved to be retried
+    #[private]
+    pub fn on_ntv_mint(&mut self, account_id: AccountId, amount: U128) -> bool {
+        if let PromiseResult::Successful(_) = env::promise_result(0) {
+            self.ntv_total_minted += amount.0;
+            let mut rewards = self.ntv_rewards.get(&account_id).unwrap_or_default();
+            rewards.claimed += amount.0;
+            self.ntv_rewards.insert(&account_id, &rewards);
+            return true;
+        }
+        let mut failed_mints = self.failed_mints.get(&account_id).unwrap_or_default();
+        failed_mints.push(FailedMint {
+            amount,
+            failed_at: env::block_timestamp(),
+        });
+        self.failed_mints.insert(&account_id, &failed_mints);
+        log_event(
+            "ntv_mint_failed",
+            json!({ "account_id": account_id, "amount": amount }),
+        );
+        false
+    }
+
+    //mint again all the failed mints of an account in one call, anyone can call it
+    pub fn retry_failed_mints(&mut self, account_id: AccountId) -> U128 {
+        self.assert_not_paused(Operation::NtvMinting);
+        let failed_mints = self
+            .failed_mints
+            .remove(&account_id)
+            .expect("There are no failed mints for this account");
+        let amount: u128 = failed_mints.iter().map(|failed_mint| failed_mint.amount.0).sum();
+        self.internal_mint_ntv(&account_id, amount);
+        log_event(
+            "ntv_mint_retried",
+            json!({ "account_id": account_id, "amount": U128(amount) }),
+        );
+        U128(amount)
+    }
+
+    //lifetime cap of the NTV rewards, it cannot be lower than the NTV already emitted
+    pub fn set_ntv_emission_cap(&mut self, cap: Option<U128>) {
+        self.assert_role(Role::Admin);
+        let cap = cap.map(u128::from);
+        if let Some(cap) = cap {
+            assert!(
+                cap >= self.ntv_total_emitted,
+                "The cap cannot be lower than the NTV already emitted"
+            );
+        }
+        self.ntv_emission_cap = cap;
+        log_event("config_updated", json!({ "ntv_emission_cap": cap.map(U128) }));
+    }
+
+    pub fn set_ntv_emission_schedule(&mut self, start_time: u64, epoch_length: u64, decay: u64) {
+        self.assert_role(Role::Admin);
+        assert!(decay <= BASIS_POINTS, "The decay cannot be greater than {}", BASIS_POINTS);
+        self.ntv_emission_schedule = EmissionSchedule {
+            start_time,
+            epoch_length,
+            decay,
+        };
+        log_event(
+            "config_updated",
+            json!({ "ntv_emission_schedule": self.ntv_emission_schedule }),
+        );
+    }
+
+    pub fn get_emission_state(&self) -> EmissionState {
+        let now = env::block_timestamp();
+        EmissionState {
+            cap: self.ntv_emission_cap.map(U128),
+            total_emitted: U128(self.ntv_total_emitted),
+            total_minted: U128(self.ntv_total_minted),
+            remaining_allowance: self.internal_remaining_allowance().map(U128),
+            schedule: self.ntv_emission_schedule.clone(),
+            current_epoch: self.ntv_emission_schedule.epoch(now),
+            current_factor: self.ntv_emission_schedule.factor(now),
+        }
+    }
+
+    pub fn get_failed_mints(&self, account_id: AccountId) -> Vec<FailedMint> {
+        self.failed_mints.get(&account_id).unwrap_or_default()
+    }
+
+    pub fn get_pending_rewards(&self, account_id: AccountId) -> PendingRewards {
+        let mut rewards = self.ntv_rewards.get(&account_id).unwrap_or_default();
+        rewards.settle(env::block_timestamp());
+        PendingRewards {
+            claimable: U128(rewards.unlocked),
+            locked: U128(rewards.locked()),
+            vesting_end: rewards.vesting_end(),
+            claimed: U128(rewards.claimed),
+        }
+    }
+
+    pub fn set_ntv_multipliers(&mut self, lender_multiply: u128, borrower_multiply: u128) {
+        self.assert_role(Role::Admin);
+        self.ntv_lender_multiply = lender_multiply;
+        self.ntv_borrower_multiply = borrower_multiply;
+        log_event(
+            "config_updated",
+            json!({
+                "ntv_lender_multiply": U128(lender_multiply),
+                "ntv_borrower_multiply": U128(borrower_multiply),
+            }),
+        );
+    }
+
+    pub fn set_ntv_reward_rules(&mut self, reward_rules: RewardRules) {
+        self.assert_role(Role::Admin);
+        assert!(
+            reward_rules.min_loan_age <= MAX_MIN_LOAN_AGE,
+            "The minimum loan age cannot be greater than {}",
+            MAX_MIN_LOAN_AGE
+        );
+        assert!(
+            reward_rules.block_window <= MAX_BLOCK_WINDOW,
+            "The block window cannot be greater than {}",
+            MAX_BLOCK_WINDOW
+        );
+        assert!(
+            reward_rules.relist_cooldown <= MAX_PAYMENT_PERIOD,
+            "The relist cooldown cannot be greater than {}",
+            MAX_PAYMENT_PERIOD
+        );
+        log_event(
+            "config_updated",
+            json!({ "ntv_reward_rules": reward_rules }),
+        );
+        self.ntv_reward_rules = reward_rules;
+    }
+
+    pub fn set_ntv_reward_basis(&mut self, reward_basis: RewardBasis) -> RewardBasis {
+        self.assert_role(Role::Admin);
+        self.ntv_reward_basis = reward_basis;
+        log_event(
+            "config_updated",
+            json!({ "ntv_reward_basis": reward_basis }),
+        );
+        self.ntv_reward_basis
+    }
+
+    //boost the NTV rewards of the loans of a collection, None removes the boost
+    pub fn set_collection_boost(&mut self, nft_contract: AccountId, boost: Option<u64>) {
+        self.assert_role(Role::CollectionCurator);
+        if let Some(boost) = boost {
+            assert!(
+                boost <= MAX_COLLECTION_BOOST,
+                "The boost cannot be greater than {}",
+                MAX_COLLECTION_BOOST
+            );
+            self.collection_boosts.insert(&nft_contract, &boost);
+        } else {
+            self.collection_boosts.remove(&nft_contract);
+        }
+        log_event(
+            "collection_boost_updated",
+            json!({ "nft_contract": nft_contract, "boost": boost }),
+        );
+    }
+
+    pub fn get_collection_boost(&self, nft_contract: AccountId) -> u64 {
+        self.collection_boosts.get(&nft_contract).unwrap_or(BOOST_BASE)
+    }
+
+    pub fn get_collection_boosts(&self, from_index: Option<U128>, limit: Option<u64>) -> Vec<CollectionBoost> {
+        let start = u128::from(from_index.unwrap_or(U128(0)));
+
+        self.collection_boosts
+            .iter()
+            .skip(start as usize)
+            .take(limit.unwrap_or(50) as usize)
+            .map(|(nft_contract, boost)| CollectionBoost { nft_contract, boost })
+            .collect()
+    }
+
+    //duration of the vesting for the new rewards, 0 to make them claimable right away
+    pub fn set_ntv_vesting_period(&mut self, ntv_vesting_period: u64) -> u64 {
+        self.assert_role(Role::Admin);
+        assert!(
+            ntv_vesting_period <= MAX_PAYMENT_PERIOD,
+            "The vesting period cannot be greater than {}",
+            MAX_PAYMENT_PERIOD
+        );
+        self.ntv_vesting_period = ntv_vesting_period;
+        log_event(
+            "config_updated",
+            json!({ "ntv_vesting_period": ntv_vesting_period }),
+        );
+        self.ntv_vesting_period
+    }
+}
+
+impl NFTLoans {
+    //mint on the NTV contract, the callback keeps the amount if it fails
+    pub(crate) fn internal_mint_ntv(&self, account_id: &AccountId, amount: u128) -> Promise {
+        ext_nft::mint(
+            account_id.clone(),
+            amount.to_string(),
+            self.ntv_token_contract.clone(),
+            1,
+            GAS_FOR_NTV_MINT,
+        )
+        .then(ext_self::on_ntv_mint(
+            account_id.clone(),
+            U128(amount),
+            env::current_account_id(),
+            0,
+            GAS_FOR_RESOLVE_MINT,
+        ))
+    }
+
+    //amount of the loan used to calculate its rewards when it is payed
+    pub(crate) fn internal_reward_basis(&self, loan: &Loan) -> u128 {
+        match self.ntv_reward_basis {
+            RewardBasis::Principal => loan.loan_requested,
+            RewardBasis::InterestPaid => loan.loan_payback - loan.loan_requested,
+            RewardBasis::TimeWeightedPrincipal => {
+                let elapsed = env::block_timestamp() - loan.loan_time.unwrap_or(loan.submission_time);
+                pro_rata(loan.loan_requested, elapsed, loan.loan_period)
+            }
+        }
+    }
+
+    //reason to withhold the rewards of a payed loan that looks like wash trading
+    pub(crate) fn internal_withheld_reason(&self, loan: &Loan) -> Option<&'static str> {
+        let rules = &self.ntv_reward_rules;
+        if !loan.rewards_eligible {
+            return Some("relisted during the cooldown");
+        }
+        let loan_time = loan.loan_time.unwrap_or(loan.submission_time);
+        if env::block_timestamp() < loan_time.saturating_add(rules.min_loan_age) {
+            return Some("payed before the minimum loan age");
+        }
+        if let Some(loan_block) = loan.loan_block {
+            if env::block_height() < loan_block.saturating_add(rules.block_window) {
+                return Some("payed within the block window");
+            }
+        }
+        None
+    }
+
+    //record the NTV rewards of the borrower and the lender of a payed loan
+    pub(crate) fn internal_reward_loan(&mut self, loan: &Loan) {
+        if let Some(reason) = self.internal_withheld_reason(loan) {
+            log_event(
+                "ntv_rewards_withheld",
+                json!({
+                    "nft_contract": loan.nft_contract,
+                    "nft_id": loan.nft_id,
+                    "reason": reason,
+                }),
+            );
+            return;
+        }
+        let basis = self.internal_reward_basis(loan);
+        let boost = self.collection_boosts.get(&loan.nft_contract).unwrap_or(BOOST_BASE);
+        let lender_reward = reward_amount(basis, self.ntv_lender_multiply, boost);
+        let borrower_reward = reward_amount(basis, self.ntv_borrower_multiply, boost);
+
+        if let Some(loaner_id) = loan.loaner_id.clone() {
+            self.internal_accrue_ntv(&loaner_id, lender_reward);
+        }
+        self.internal_accrue_ntv(&loan.nft_owner.clone(), borrower_reward);
+
+        self.internal_reward_campaigns(loan, basis);
+    }
+
+    pub(crate) fn internal_remaining_allowance(&self) -> Option<u128> {
+        self.ntv_emission_cap
+            .map(|cap| cap.saturating_sub(self.ntv_total_emitted))
+    }
+
+    //record NTV rewards for an account, the new rewards vest over their own period
+    //without changing the schedule of the rewards already vesting
+    pub(crate) fn internal_accrue_ntv(&mut self, account_id: &AccountId, amount: u128) -> u128 {
+        if !self.is_minting_ntv {
+            env::log_str("the nvt token minting is disabled");
+            return 0;
+        }
+        let now = env::block_timestamp();
+        //the rewards decay with the emission schedule and stop when the cap is reached
+        let amount = apply_bps(amount, self.ntv_emission_schedule.factor(now), Rounding::Down);
+        let amount = match self.internal_remaining_allowance() {
+            Some(remaining) => min(amount, remaining),
+            None => amount,
+        };
+        if amount == 0 {
+            return 0;
+        }
+        let mut rewards = self.ntv_rewards.get(account_id).unwrap_or_default();
+        rewards.settle(now);
+        if self.ntv_vesting_period == 0 {
+            rewards.unlocked += amount;
+        } else {
+            rewards.lock(amount, now, self.ntv_vesting_period);
+        }
+        self.ntv_rewards.insert(account_id, &rewards);
+        self.ntv_total_emitted += amount;
+        self.internal_update_account_stats(account_id, |stats| stats.ntv_earned.0 += amount);
+        log_event(
+            "ntv_rewards_accrued",
+            json!({ "account_id": account_id, "amount": U128(amount) }),
+        );
+        amount
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PERIOD: u64 = 10 * NS_PER_DAY;
+
+    fn assert_consistent(rewards: &RewardAccount) {
+        for tranche in rewards.tranches.iter() {
+            assert!(tranche.vested <= tranche.amount);
+        }
+    }
+
+    #[test]
+    fn settle_vests_linearly() {
+        let mut rewards = RewardAccount::default();
+        rewards.lock(1000, 0, PERIOD);
+        rewards.settle(PERIOD / 4);
+        assert_eq!(rewards.unlocked, 250);
+        assert_eq!(rewards.locked(), 750);
+        //settling again at the same time vests nothing more
+        rewards.settle(PERIOD / 4);
+        assert_eq!(rewards.unlocked, 250);
+        rewards.settle(PERIOD);
+        assert_eq!(rewards.unlocked, 1000);
+        assert_eq!(rewards.locked(), 0);
+        assert!(rewards.tranches.is_empty());
+        assert_eq!(rewards.vesting_end(), 0);
+    }
+
+    #[test]
+    fn settle_before_the_start_vests_nothing() {
+        let mut rewards = RewardAccount::default();
+        rewards.lock(1000, NS_PER_DAY, PERIOD);
+        rewards.settle(0);
+        assert_eq!(rewards.unlocked, 0);
+        assert_eq!(rewards.locked(), 1000);
+    }
+
+    #[test]
+    fn lock_merges_the_rewards_of_the_same_day() {
+        let mut rewards = RewardAccount::default();
+        rewards.lock(1000, 0, PERIOD);
+        rewards.lock(500, NS_PER_DAY - 1, PERIOD);
+        assert_eq!(rewards.tranches.len(), 1);
+        assert_eq!(rewards.locked(), 1500);
+        assert_eq!(rewards.vesting_end(), PERIOD);
+
+        rewards.lock(500, NS_PER_DAY, PERIOD);
+        assert_eq!(rewards.tranches.len(), 2);
+        assert_eq!(rewards.vesting_end(), NS_PER_DAY + PERIOD);
+    }
+
+    #[test]
+    fn new_rewards_do_not_reset_the_vesting() {
+        let mut rewards = RewardAccount::default();
+        rewards.lock(1000, 0, PERIOD);
+        rewards.settle(PERIOD / 2);
+        rewards.lock(1000, PERIOD / 2, PERIOD);
+        rewards.settle(PERIOD);
+        //the first rewards are fully vested and the new ones are half vested
+        assert_eq!(rewards.unlocked, 1500);
+        assert_eq!(rewards.locked(), 500);
+        assert_eq!(rewards.vesting_end(), PERIOD / 2 + PERIOD);
+    }
+
+    #[test]
+    fn a_vesting_period_change_only_applies_to_the_new_rewards() {
+        let mut rewards = RewardAccount::default();
+        rewards.lock(1000, 0, PERIOD);
+        rewards.lock(1000, NS_PER_DAY, 2 * PERIOD);
+        assert_eq!(rewards.tranches[0].end, PERIOD);
+        assert_eq!(rewards.tranches[1].end, NS_PER_DAY + 2 * PERIOD);
+
+        rewards.settle(PERIOD);
+        assert_eq!(rewards.tranches.len(), 1);
+        assert_eq!(rewards.unlocked, 1000 + 450);
+        rewards.settle(NS_PER_DAY + 2 * PERIOD);
+        assert_eq!(rewards.unlocked, 2000);
+    }
+
+    #[test]
+    fn vested_never_exceeds_the_amount() {
+        let mut rewards = RewardAccount::default();
+        rewards.lock(1000, 0, PERIOD);
+        //the same day rewards merged after part of the tranche vested
+        rewards.settle(NS_PER_DAY / 2);
+        assert_consistent(&rewards);
+        rewards.lock(7, NS_PER_DAY / 2, PERIOD);
+        rewards.settle(NS_PER_DAY - 1);
+        assert_consistent(&rewards);
+        rewards.lock(3, NS_PER_DAY - 1, PERIOD);
+        assert_consistent(&rewards);
+        rewards.settle(u64::MAX);
+        assert_consistent(&rewards);
+        assert_eq!(rewards.unlocked, 1010);
+        assert_eq!(rewards.locked(), 0);
+    }
+}
@@ -0,0 +1,122 @@
+use crate::*;
+
+#[near_bindgen]
+impl NFTLoans {
+    //give a role to an account, only an admin can do it
+    pub fn grant_role(&mut self, role: Role, account_id: AccountId) -> bool {
+        self.assert_role(Role::Admin);
+        let mut members = self.role_members.get(&role).unwrap_or_else(|| {
+            UnorderedSet::new(
+                StorageKey::RoleMembersInner { role }
+                    .try_to_vec()
+                    .unwrap(),
+            )
+        });
+        let granted = members.insert(&account_id);
+        self.role_members.insert(&role, &members);
+        if granted {
+            log_event(
+                "role_granted",
+                json!({
+                    "role": role,
+                    "account_id": account_id,
+                    "sender_id": env::predecessor_account_id(),
+                }),
+            );
+        }
+        granted
+    }
+
+    //remove a role from an account, only an admin can do it
+    pub fn revoke_role(&mut self, role: Role, account_id: AccountId) -> bool {
+        self.assert_role(Role::Admin);
+        self.internal_revoke_role(role, &account_id)
+    }
+
+    //an account can always give up its own role
+    pub fn renounce_role(&mut self, role: Role) -> bool {
+        let account_id = env::predecessor_account_id();
+        self.internal_revoke_role(role, &account_id)
+    }
+
+    pub fn has_role(&self, role: Role, account_id: AccountId) -> bool {
+        self.internal_has_role(role, &account_id)
+    }
+
+    //the roles granted to an account
+    pub fn get_account_roles(&self, account_id: AccountId) -> Vec<Role> {
+        ALL_ROLES
+            .iter()
+            .filter(|role| self.internal_has_role(**role, &account_id))
+            .cloned()
+            .collect()
+    }
+
+    //the accounts granted with a role using pagination, the owner is not listed
+    pub fn get_role_members(
+        &self,
+        role: Role,
+        from_index: Option<U128>,
+        limit: Option<u64>,
+    ) -> Vec<AccountId> {
+        let members = if let Some(members) = self.role_members.get(&role) {
+            members
+        } else {
+            return vec![];
+        };
+
+        let start = u128::from(from_index.unwrap_or(U128(0)));
+
+        members
+            .iter()
+            .skip(start as usize)
+            .take(limit.unwrap_or(50) as usize)
+            .collect()
+    }
+}
+
+impl NFTLoans {
+    pub(crate) fn internal_has_role(&self, role: Role, account_id: &AccountId) -> bool {
+        if *account_id == self.owner_account_id {
+            return true;
+        }
+        self.role_members
+            .get(&role)
+            .map(|members| members.contains(account_id))
+            .unwrap_or(false)
+    }
+
+    //the caller is checked with the predecessor so a DAO contract can hold the roles
+    pub(crate) fn assert_role(&self, role: Role) {
+        assert!(
+            self.internal_has_role(role, &env::predecessor_account_id()),
+            "you don't have the {:?} role",
+            role
+        );
+    }
+
+    pub(crate) fn internal_revoke_role(&mut self, role: Role, account_id: &AccountId) -> bool {
+        let mut members = if let Some(members) = self.role_members.get(&role) {
+            members
+        } else {
+            return false;
+        };
+        let revoked = members.remove(account_id);
+        if members.is_empty() {
+            self.role_members.remove(&role);
+        } else {
+            self.role_members.insert(&role, &members);
+        }
+        if revoked {
+            log_event(
+                "role_revoked",
+                json!({
+                    "role": role,
+                    "account_id": account_id,
+                    "sender_id": env::predecessor_account_id(),
+                }),
+            );
+        }
+        revoked
+    }
+}
@@ -0,0 +1,77 @@
+use crate::*;
+
+#[near_bindgen]
+impl NFTLoans {
+    pub fn get_account_stats(&self, account_id: AccountId) -> AccountStats {
+        self.account_stats.get(&account_id).unwrap_or_default()
+    }
+}
+
+impl NFTLoans {
+    pub(crate) fn internal_update_account_stats<F: FnOnce(&mut AccountStats)>(
+        &mut self,
+        account_id: &AccountId,
+        update: F,
+    ) {
+        let mut stats = self.account_stats.get(account_id).unwrap_or_default();
+        update(&mut stats);
+        self.account_stats.insert(account_id, &stats);
+    }
+
+    //update the statistics of the borrower and the lender when a loan changes of status
+    //the outstanding amounts saturate for the loans funded before the statistics existed
+    pub(crate) fn internal_record_account_stats(&mut self, loan: &Loan) {
+        let principal = loan.loan_requested;
+        let borrower = loan.nft_owner.clone();
+
+        match loan.status {
+            LoanStatus::Loaned => {
+                self.internal_update_account_stats(&borrower, |stats| {
+                    stats.loans_borrowed += 1;
+                    stats.total_borrowed.0 += principal;
+                    stats.outstanding_borrowed.0 += principal;
+                });
+                if let Some(loaner_id) = &loan.loaner_id {
+                    self.internal_update_account_stats(loaner_id, |stats| {
+                        stats.loans_lent += 1;
+                        stats.total_lent.0 += principal;
+                        stats.outstanding_lent.0 += principal;
+                    });
+                }
+            }
+            LoanStatus::Payed => {
+                let interest = loan.loan_payback - principal;
+                self.internal_update_account_stats(&borrower, |stats| {
+                    stats.loans_repaid += 1;
+                    stats.total_repaid.0 += loan.loan_payback;
+                    stats.outstanding_borrowed.0 = stats.outstanding_borrowed.0.saturating_sub(principal);
+                });
+                if let Some(loaner_id) = &loan.loaner_id {
+                    self.internal_update_account_stats(loaner_id, |stats| {
+                        stats.interest_earned.0 += interest;
+                        stats.outstanding_lent.0 = stats.outstanding_lent.0.saturating_sub(principal);
+                    });
+                }
+            }
+            LoanStatus::Expired => {
+                self.internal_update_account_stats(&borrower, |stats| {
+                    stats.loans_defaulted += 1;
+                    stats.total_defaulted.0 += principal;
+                    stats.outstanding_borrowed.0 = stats.outstanding_borrowed.0.saturating_sub(principal);
+                });
+                if let Some(loaner_id) = &loan.loaner_id {
+                    self.internal_update_account_stats(loaner_id, |stats| {
+                        stats.collateral_acquired += 1;
+                        stats.outstanding_lent.0 = stats.outstanding_lent.0.saturating_sub(principal);
+                    });
+                }
+            }
+            LoanStatus::Canceled => {
+                self.internal_update_account_stats(&borrower, |stats| {
+                    stats.loans_canceled += 1;
+                });
+            }
+            LoanStatus::Pending | LoanStatus::ListingExpired => {}
+        }
+    }
+}
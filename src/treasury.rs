@@ -0,0 +1,90 @@
+use crate::*;
+
+/// Currency of the loans paid with the native token
+pub const NEAR_CURRENCY: &str = "NEAR";
+/// Gas for the callback of a treasury withdraw
+pub const GAS_FOR_RESOLVE_WITHDRAW: Gas = Gas(5_000_000_000_000);
+
+#[near_bindgen]
+impl NFTLoans {
+    //send the accrued fees to the treasury, by default to the treasury account
+    pub fn withdraw_treasury(&mut self, amount: U128, receiver_id: Option<AccountId>) -> Promise {
+        self.assert_role(Role::FeeManager);
+        let amount = u128::from(amount);
+        assert!(amount > 0, "The amount should be greater than 0");
+        let receiver_id = receiver_id.unwrap_or_else(|| self.treasury_account_id.clone());
+
+        let mut treasury = self.internal_get_treasury(NEAR_CURRENCY);
+        assert!(amount <= treasury.balance.0, "The amount exceeds the treasury balance");
+        treasury.balance.0 -= amount;
+        treasury.total_withdrawn.0 += amount;
+        self.treasury.insert(&NEAR_CURRENCY.to_string(), &treasury);
+
+        log_event(
+            "treasury_withdraw",
+            json!({
+                "currency": NEAR_CURRENCY,
+                "amount": U128(amount),
+                "receiver_id": receiver_id,
+            }),
+        );
+
+        Promise::new(receiver_id.clone()).transfer(amount).then(
+            ext_self::on_treasury_withdraw(
+                U128(amount),
+                receiver_id,
+                env::current_account_id(),
+                0,
+                GAS_FOR_RESOLVE_WITHDRAW,
+            ),
+        )
+    }
+
+    //if the transfer failed the amount is returned to the treasury balance
+    #[private]
+    pub fn on_treasury_withdraw(&mut self, amount: U128, receiver_id: AccountId) -> bool {
+        if let PromiseResult::Successful(_) = env::promise_result(0) {
+            return true;
+        }
+        let amount = u128::from(amount);
+        let mut treasury = self.internal_get_treasury(NEAR_CURRENCY);
+        treasury.balance.0 += amount;
+        treasury.total_withdrawn.0 -= amount;
+        self.treasury.insert(&NEAR_CURRENCY.to_string(), &treasury);
+        log_event(
+            "treasury_withdraw_failed",
+            json!({
+                "currency": NEAR_CURRENCY,
+                "amount": U128(amount),
+                "receiver_id": receiver_id,
+            }),
+        );
+        false
+    }
+
+    pub fn get_treasury_balance(&self, currency: Option<String>) -> TreasuryBalance {
+        self.internal_get_treasury(&currency.unwrap_or_else(|| NEAR_CURRENCY.to_string()))
+    }
+}
+
+impl NFTLoans {
+    pub(crate) fn internal_get_treasury(&self, currency: &str) -> TreasuryBalance {
+        self.treasury.get(&currency.to_string()).unwrap_or_default()
+    }
+
+    //keep the fee in the contract until the fee manager withdraws it
+    pub(crate) fn internal_accrue_fee(&mut self, currency: &str, amount: u128) {
+        if amount == 0 {
+            return;
+        }
+        let mut treasury = self.internal_get_treasury(currency);
+        treasury.balance.0 += amount;
+        treasury.total_collected.0 += amount;
+        self.treasury.insert(&currency.to_string(), &treasury);
+        self.internal_record_snapshot();
+        log_event(
+            "fee_accrued",
+            json!({ "currency": currency, "amount": U128(amount) }),
+        );
+    }
+}